@@ -0,0 +1,376 @@
+use crate::error::Error;
+use crate::expr;
+use crate::expr::FieldPath;
+use crate::location;
+use crate::location::DataLocation;
+use crate::pipe::{NextSummary, Summary};
+use crate::runtime::Runtime;
+use crate::tabular::{TableWriter, TabularOptions};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+mod functions {
+    pub(crate) const COUNT: &str = "count";
+    pub(crate) const SUM: &str = "sum";
+    pub(crate) const MEAN: &str = "mean";
+    pub(crate) const MIN: &str = "min";
+    pub(crate) const MAX: &str = "max";
+    pub(crate) const DISTINCT: &str = "distinct";
+    pub(crate) const ALL: [&str; 6] = [COUNT, SUM, MEAN, MIN, MAX, DISTINCT];
+}
+
+#[derive(Copy, Clone)]
+enum Function {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    Distinct,
+}
+
+struct Aggregate {
+    function: Function,
+    field: Option<(String, FieldPath)>,
+}
+
+pub(crate) struct SortSpec {
+    column: String,
+    descending: bool,
+}
+
+pub(crate) struct AggregateOptions {
+    by: Vec<(String, FieldPath)>,
+    aggregates: Vec<Aggregate>,
+    sort: Option<SortSpec>,
+    tabular: TabularOptions,
+}
+
+enum Accumulator {
+    Count(u64),
+    Sum(f64),
+    Mean { sum: f64, n: u64 },
+    Min(Option<f64>),
+    Max(Option<f64>),
+    Distinct(BTreeSet<String>),
+}
+
+struct Group {
+    key_values: Vec<Value>,
+    accumulators: Vec<Accumulator>,
+}
+
+struct AggregateSummary<'a> {
+    options: &'a AggregateOptions,
+    /// Keyed by the JSON of the group values, so that null and "null" stay apart.
+    groups: BTreeMap<Vec<String>, Group>,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::Count => write!(f, "{}", functions::COUNT),
+            Function::Sum => write!(f, "{}", functions::SUM),
+            Function::Mean => write!(f, "{}", functions::MEAN),
+            Function::Min => write!(f, "{}", functions::MIN),
+            Function::Max => write!(f, "{}", functions::MAX),
+            Function::Distinct => write!(f, "{}", functions::DISTINCT),
+        }
+    }
+}
+
+impl TryFrom<&str> for Function {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            functions::COUNT => Ok(Function::Count),
+            functions::SUM => Ok(Function::Sum),
+            functions::MEAN => Ok(Function::Mean),
+            functions::MIN => Ok(Function::Min),
+            functions::MAX => Ok(Function::Max),
+            functions::DISTINCT => Ok(Function::Distinct),
+            _ => Err(Error::from(format!(
+                "Unknown aggregate function '{}'. Known functions are '{}'.", string,
+                functions::ALL.join("', '")
+            )))
+        }
+    }
+}
+
+impl Aggregate {
+    fn count() -> Aggregate { Aggregate { function: Function::Count, field: None } }
+    fn parse(string: &str) -> Result<Aggregate, Error> {
+        match string.strip_suffix(')').and_then(|string| string.split_once('(')) {
+            Some((name, field)) => {
+                let function = Function::try_from(name.trim())?;
+                let field = field.trim();
+                if field.is_empty() {
+                    Err(Error::from(format!("Aggregate '{}' needs a field name.", string)))
+                } else {
+                    let path = FieldPath::parse(field)?;
+                    Ok(Aggregate { function, field: Some((field.to_string(), path)) })
+                }
+            }
+            None => {
+                let function = Function::try_from(string)?;
+                match function {
+                    Function::Count => Ok(Aggregate { function, field: None }),
+                    _ => Err(Error::from(format!(
+                        "Aggregate '{}' needs a field name, as in '{}(<field>)'.", string, string
+                    ))),
+                }
+            }
+        }
+    }
+    fn column_name(&self) -> String {
+        match &self.field {
+            Some((field, _)) => format!("{}({})", self.function, field),
+            None => self.function.to_string(),
+        }
+    }
+    fn new_accumulator(&self) -> Accumulator {
+        match self.function {
+            Function::Count => Accumulator::Count(0),
+            Function::Sum => Accumulator::Sum(0.0),
+            Function::Mean => Accumulator::Mean { sum: 0.0, n: 0 },
+            Function::Min => Accumulator::Min(None),
+            Function::Max => Accumulator::Max(None),
+            Function::Distinct => Accumulator::Distinct(BTreeSet::new()),
+        }
+    }
+}
+
+impl SortSpec {
+    pub(crate) fn parse(string: &str) -> SortSpec {
+        if let Some(column) = string.strip_suffix(":desc") {
+            SortSpec { column: column.to_string(), descending: true }
+        } else {
+            let column = string.strip_suffix(":asc").unwrap_or(string);
+            SortSpec { column: column.to_string(), descending: false }
+        }
+    }
+}
+
+impl Accumulator {
+    fn add(&mut self, value: Option<&Value>) {
+        match self {
+            Accumulator::Count(count) => {
+                if value.is_none_or(|value| !value.is_null()) {
+                    *count += 1;
+                }
+            }
+            Accumulator::Sum(sum) => {
+                if let Some(number) = value.and_then(expr::as_number) {
+                    *sum += number;
+                }
+            }
+            Accumulator::Mean { sum, n } => {
+                if let Some(number) = value.and_then(expr::as_number) {
+                    *sum += number;
+                    *n += 1;
+                }
+            }
+            Accumulator::Min(min) => {
+                if let Some(number) = value.and_then(expr::as_number) {
+                    *min = Some(min.map_or(number, |min| min.min(number)));
+                }
+            }
+            Accumulator::Max(max) => {
+                if let Some(number) = value.and_then(expr::as_number) {
+                    *max = Some(max.map_or(number, |max| max.max(number)));
+                }
+            }
+            Accumulator::Distinct(values) => {
+                if let Some(value) = value.filter(|value| !value.is_null()) {
+                    values.insert(key_string(value));
+                }
+            }
+        }
+    }
+    fn value(&self) -> Value {
+        match self {
+            Accumulator::Count(count) => Value::from(*count),
+            Accumulator::Sum(sum) => Value::from(*sum),
+            Accumulator::Mean { sum, n } => {
+                if *n > 0 { Value::from(*sum / (*n as f64)) } else { Value::Null }
+            }
+            Accumulator::Min(min) => min.map(Value::from).unwrap_or(Value::Null),
+            Accumulator::Max(max) => max.map(Value::from).unwrap_or(Value::Null),
+            Accumulator::Distinct(values) => Value::from(values.len()),
+        }
+    }
+}
+
+impl AggregateOptions {
+    pub(crate) fn new(by: Vec<String>, sort: Option<SortSpec>, tabular: TabularOptions)
+        -> Result<AggregateOptions, Error> {
+        let mut tabular = tabular;
+        let mut aggregates: Vec<Aggregate> = Vec::new();
+        for spec in tabular.columns.drain(..) {
+            aggregates.push(Aggregate::parse(&spec)?);
+        }
+        if aggregates.is_empty() {
+            aggregates.push(Aggregate::count());
+        }
+        let by = by.into_iter().map(|field| {
+            let path = FieldPath::parse(&field)?;
+            Ok((field, path))
+        }).collect::<Result<Vec<(String, FieldPath)>, Error>>()?;
+        Ok(AggregateOptions { by, aggregates, sort, tabular })
+    }
+    fn columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = self.by.iter().map(|(field, _)| field.clone()).collect();
+        columns.extend(self.aggregates.iter().map(|aggregate| aggregate.column_name()));
+        columns
+    }
+}
+
+impl Summary for AggregateSummary<'_> {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let value: Value = serde_json::from_str(&line)?;
+        let AggregateSummary { options, mut groups } = self;
+        if let Value::Object(map) = &value
+            && options.tabular.filter.as_ref().is_none_or(|filter| filter.matches(map)) {
+            add_to_groups(options, &mut groups, map);
+        }
+        Ok(NextSummary { summary: AggregateSummary { options, groups } })
+    }
+}
+
+fn add_to_groups(options: &AggregateOptions, groups: &mut BTreeMap<Vec<String>, Group>,
+                 map: &Map<String, Value>) {
+    let key_values: Vec<Value> =
+        options.by.iter().map(|(_, path)| path.resolve(map).cloned().unwrap_or(Value::Null))
+            .collect();
+    let key: Vec<String> = key_values.iter().map(|value| value.to_string()).collect();
+    let group = groups.entry(key).or_insert_with(|| Group {
+        key_values,
+        accumulators:
+            options.aggregates.iter().map(|aggregate| aggregate.new_accumulator()).collect(),
+    });
+    for (aggregate, accumulator) in
+        options.aggregates.iter().zip(group.accumulators.iter_mut()) {
+        let value = aggregate.field.as_ref()
+            .map(|(_, path)| path.resolve(map).unwrap_or(&Value::Null));
+        accumulator.add(value);
+    }
+}
+
+fn key_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn compare_cells(lhs: &Value, rhs: &Value) -> Ordering {
+    match (lhs, rhs) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ => match (expr::as_number(lhs), expr::as_number(rhs)) {
+            (Some(lhs), Some(rhs)) => lhs.total_cmp(&rhs),
+            _ => key_string(lhs).cmp(&key_string(rhs)),
+        },
+    }
+}
+
+pub(crate) fn aggregate(runtime: &Runtime, location: &DataLocation, options: &AggregateOptions)
+    -> Result<(), Error> {
+    let columns = options.columns();
+    let sort_index = match &options.sort {
+        Some(sort) => {
+            let index =
+                columns.iter().position(|column| *column == sort.column).ok_or_else(|| {
+                    Error::from(format!(
+                        "Cannot sort by '{}'. Output columns are '{}'.", sort.column,
+                        columns.join("', '")
+                    ))
+                })?;
+            Some((index, sort.descending))
+        }
+        None => None,
+    };
+    let summary = AggregateSummary { options, groups: BTreeMap::new() };
+    let summary = location::process_from(runtime, location, summary)?;
+    let mut rows: Vec<Vec<Value>> =
+        summary.groups.into_values().map(|group| {
+            let mut row = group.key_values;
+            row.extend(group.accumulators.iter().map(|accumulator| accumulator.value()));
+            row
+        }).collect();
+    if let Some((index, descending)) = sort_index {
+        rows.sort_by(|a, b| {
+            let ordering = compare_cells(&a[index], &b[index]);
+            if descending { ordering.reverse() } else { ordering }
+        });
+    }
+    let tabular = &options.tabular;
+    let mut table_writer = TableWriter::new(tabular.format, columns, tabular.output.as_deref())?;
+    for row in rows {
+        table_writer.write_row(&row)?;
+    }
+    table_writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn aggregate_lines(by: &[&str], aggregates: &[&str], lines: &[Value]) -> Vec<Vec<Value>> {
+        let mut tabular = TabularOptions::new();
+        tabular.columns = aggregates.iter().map(|aggregate| aggregate.to_string()).collect();
+        let by = by.iter().map(|field| field.to_string()).collect();
+        let options = AggregateOptions::new(by, None, tabular).unwrap();
+        let mut summary = AggregateSummary { options: &options, groups: BTreeMap::new() };
+        for line in lines {
+            summary = summary.next(line.to_string()).unwrap().summary;
+        }
+        summary.groups.into_values().map(|group| {
+            let mut row = group.key_values;
+            row.extend(group.accumulators.iter().map(|accumulator| accumulator.value()));
+            row
+        }).collect()
+    }
+
+    #[test]
+    fn aggregates_per_group() {
+        let lines = [
+            json!({ "g": "a", "x": 1 }), json!({ "g": "a", "x": 3 }), json!({ "g": "b", "x": 2 })
+        ];
+        let rows = aggregate_lines(&["g"], &["count", "sum(x)", "mean(x)", "max(x)"], &lines);
+        assert_eq!(rows, vec![
+            vec![json!("a"), json!(2), json!(4.0), json!(2.0), json!(3.0)],
+            vec![json!("b"), json!(1), json!(2.0), json!(2.0), json!(2.0)],
+        ]);
+    }
+
+    #[test]
+    fn groups_and_aggregates_resolve_nested_paths() {
+        let lines = [
+            json!({ "a": { "b": "x" }, "v": [5] }), json!({ "a": { "b": "y" }, "v": [7] })
+        ];
+        let rows = aggregate_lines(&["a.b"], &["sum(v[0])"], &lines);
+        assert_eq!(rows, vec![vec![json!("x"), json!(5.0)], vec![json!("y"), json!(7.0)]]);
+    }
+
+    #[test]
+    fn null_and_missing_differ_from_string_null() {
+        let lines = [json!({ "g": "null" }), json!({ "g": null }), json!({})];
+        let rows = aggregate_lines(&["g"], &["count"], &lines);
+        assert_eq!(rows.len(), 2);
+        assert!(rows.contains(&vec![json!("null"), json!(1)]));
+        assert!(rows.contains(&vec![Value::Null, json!(2)]));
+    }
+
+    #[test]
+    fn distinct_ignores_nulls() {
+        let lines = [json!({ "x": 1 }), json!({ "x": 1 }), json!({ "x": 2 }), json!({})];
+        let rows = aggregate_lines(&[], &["distinct(x)"], &lines);
+        assert_eq!(rows, vec![vec![json!(2)]]);
+    }
+}
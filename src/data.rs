@@ -1,31 +1,53 @@
+use crate::distill::confidence::Confidence;
+use crate::distill::distiller;
+use crate::distill::distiller::AnyDistiller;
+use crate::distill::four_dn;
 use crate::error::Error;
+use crate::io;
+use crate::json;
+use crate::location::{DataFormat, DataLocation, Location, RELEASE_PLACEHOLDER};
 use crate::s3::S3Uri;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::rc::Rc;
 
+const SOURCES_FILE_ENV: &str = "LEMBIC_SOURCES";
+const SOURCES_FILE: &str = "sources.json";
 
 pub(crate) struct Selection {
-    pub(crate) sources: Vec<Source>,
-    pub(crate) with_variants: bool,
-}
-#[derive(Copy, Clone)]
-pub(crate) enum Source {
-    GtexTstat,
-    GtexSldsc,
-    FourDnGeneBio,
-    ExRnaGeneCounts,
-}
-
-pub(crate) const ALL_SOURCES: [Source; 4] = [
-    Source::GtexTstat,
-    Source::GtexSldsc,
-    Source::FourDnGeneBio,
-    Source::ExRnaGeneCounts,
-];
+    pub(crate) sources: Vec<SelectedSource>,
+}
+
+pub(crate) struct SelectedSource {
+    pub(crate) source: SourceDef,
+    pub(crate) assertions: BTreeSet<String>,
+}
+
 const DIG_ANALYSIS_CFDE: &str = "dig-analysis-cfde";
 
+#[derive(Clone)]
+pub(crate) struct SourceDef {
+    pub(crate) name: String,
+    pub(crate) location: DataLocation,
+    pub(crate) distiller: Rc<dyn AnyDistiller>,
+    pub(crate) description: String,
+    pub(crate) release: Option<String>,
+    pub(crate) confidence: Option<Rc<Confidence>>,
+}
+
+pub(crate) struct Registry {
+    sources: Vec<SourceDef>,
+    presets: BTreeMap<String, Vec<String>>,
+}
+
 pub(crate) struct PredefDataSource {
     pub(crate) bucket: &'static str,
     pub(crate) key: &'static str,
+    pub(crate) distiller: &'static str,
+    pub(crate) description: &'static str,
 }
 
 mod names {
@@ -35,102 +57,461 @@ mod names {
     pub(crate) const EXRNA_GENE_COUNTS: &str = "exrna_gene_counts";
 }
 
+mod selections {
+    pub(crate) const ALL: &str = "all";
+    pub(crate) const THREE: &str = "three";
+    pub(crate) const NOVARS: &str = "novars";
+    pub(crate) const EXCLUDE: char = '-';
+    pub(crate) const ASSERTION: char = ':';
+}
+
+mod keys {
+    pub(crate) const SOURCES: &str = "sources";
+    pub(crate) const PRESETS: &str = "presets";
+    pub(crate) const NAME: &str = "name";
+    pub(crate) const LOCATION: &str = "location";
+    pub(crate) const BUCKET: &str = "bucket";
+    pub(crate) const PREFIX: &str = "prefix";
+    pub(crate) const KEY: &str = "key";
+    pub(crate) const PATH: &str = "path";
+    pub(crate) const FORMAT: &str = "format";
+    pub(crate) const DISTILLER: &str = "distiller";
+    pub(crate) const DESCRIPTION: &str = "description";
+    pub(crate) const RELEASE: &str = "release";
+    pub(crate) const SPEC: &str = "spec";
+    pub(crate) const CONFIDENCE: &str = "confidence";
+}
+
 pub(crate) mod sources {
+    use super::names;
     use super::PredefDataSource;
     pub(crate) const GTEX_TSTAT: PredefDataSource =
         PredefDataSource::new(super::DIG_ANALYSIS_CFDE,
-                              "GTEx/bioindex/tstat/part-00000.json");
+                              "GTEx/bioindex/tstat/part-00000.json",
+                              names::GTEX_TSTAT,
+                              "GTEx gene expression t-statistics per biosample");
     pub(crate) const GTEX_SLSDC: PredefDataSource =
         PredefDataSource::new(super::DIG_ANALYSIS_CFDE,
-                              "GTEx/bioindex/sldsc/mondo/part-00000.json");
+                              "GTEx/bioindex/sldsc/mondo/part-00000.json",
+                              names::GTEX_SLSDC,
+                              "GTEx S-LDSC enrichment of MONDO phenotypes per tissue");
     pub(crate) const FOURDN_GENE_BIO: PredefDataSource =
         PredefDataSource::new(super::DIG_ANALYSIS_CFDE,
-                              "4DN/bioindex/gene-bio/part-00000.json");
+                              "4DN/bioindex/gene-bio/part-00000.json",
+                              names::FOURDN_GENE_BIO,
+                              "4DN lead SNP to gene predictions with MONDO phenotypes");
     pub(crate) const EXRNA_GENE_COUNTS: PredefDataSource =
         PredefDataSource::new(super::DIG_ANALYSIS_CFDE,
-                              "exRNA/bioindex/gene-counts/part-00000.json");
+                              "exRNA/bioindex/gene-counts/part-00000.json",
+                              names::EXRNA_GENE_COUNTS,
+                              "exRNA RNA-binding protein read counts per gene");
+    pub(crate) const ALL: [(&str, PredefDataSource); 4] = [
+        (names::GTEX_TSTAT, GTEX_TSTAT),
+        (names::GTEX_SLSDC, GTEX_SLSDC),
+        (names::FOURDN_GENE_BIO, FOURDN_GENE_BIO),
+        (names::EXRNA_GENE_COUNTS, EXRNA_GENE_COUNTS),
+    ];
 }
+
 impl PredefDataSource {
-    pub(crate) const fn new(bucket: &'static str, key: &'static str)
+    pub(crate) const fn new(bucket: &'static str, key: &'static str,
+                            distiller: &'static str, description: &'static str)
         -> PredefDataSource {
-        PredefDataSource { bucket, key }
+        PredefDataSource { bucket, key, distiller, description }
     }
     pub(crate) fn to_s3uri(&self) -> S3Uri {
         S3Uri::from_strs(self.bucket, self.key)
     }
+    fn to_source_def(&self, name: &str) -> Result<SourceDef, Error> {
+        Ok(SourceDef {
+            name: name.to_string(),
+            location: DataLocation::s3(self.to_s3uri()),
+            distiller: distiller::create_distiller(self.distiller, None)?,
+            description: self.description.to_string(),
+            release: None,
+            confidence: None,
+        })
+    }
 }
 
-impl Source {
-    pub(crate) fn get_s3uri(&self) -> S3Uri {
-        match self {
-            Source::GtexTstat => sources::GTEX_TSTAT.to_s3uri(),
-            Source::GtexSldsc => sources::GTEX_SLSDC.to_s3uri(),
-            Source::FourDnGeneBio => sources::FOURDN_GENE_BIO.to_s3uri(),
-            Source::ExRnaGeneCounts => sources::EXRNA_GENE_COUNTS.to_s3uri(),
-        }
+impl Display for SourceDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
     }
 }
 
-impl Display for Source {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Source::GtexTstat => write!(f, "{}", names::GTEX_TSTAT),
-            Source::GtexSldsc => write!(f, "{}", names::GTEX_SLSDC),
-            Source::FourDnGeneBio => write!(f, "{}", names::FOURDN_GENE_BIO),
-            Source::ExRnaGeneCounts => write!(f, "{}", names::EXRNA_GENE_COUNTS),
+impl SourceDef {
+    fn from_json(map: &Map<String, Value>) -> Result<SourceDef, Error> {
+        let name = json::get_string(map, keys::NAME)?;
+        let location = parse_location(map.get(keys::LOCATION)).map_err(|error| {
+            Error::wrap(format!("Invalid location for source '{}'", name), error)
+        })?;
+        let format = match map.get(keys::FORMAT) {
+            Some(_) => DataFormat::try_from(json::get_string(map, keys::FORMAT)?.as_str())?,
+            None => DataFormat::JsonLines,
+        };
+        let distiller = json::get_string(map, keys::DISTILLER)?;
+        let spec = match map.get(keys::SPEC) {
+            Some(Value::Object(spec)) => Some(spec),
+            Some(value) => {
+                return Err(Error::from(format!(
+                    "Expected object field '{}', but got {}", keys::SPEC, value
+                )));
+            }
+            None => None,
+        };
+        let distiller = distiller::create_distiller(&distiller, spec).map_err(|error| {
+            Error::wrap(format!("Invalid distiller for source '{}'", name), error)
+        })?;
+        let description = match map.get(keys::DESCRIPTION) {
+            Some(_) => json::get_string(map, keys::DESCRIPTION)?,
+            None => String::new(),
+        };
+        let release = match map.get(keys::RELEASE) {
+            Some(_) => Some(json::get_string(map, keys::RELEASE)?),
+            None => None,
+        };
+        let confidence = match map.get(keys::CONFIDENCE) {
+            Some(value) => Some(Rc::new(Confidence::from_json(value).map_err(|error| {
+                Error::wrap(format!("Invalid confidence for source '{}'", name), error)
+            })?)),
+            None => None,
+        };
+        let location = DataLocation::new(location, format);
+        Ok(SourceDef { name, location, distiller, description, release, confidence })
+    }
+    pub(crate) fn resolve_release(&self, release: Option<&str>) -> Result<SourceDef, Error> {
+        if !self.location.needs_release() {
+            return Ok(self.clone());
+        }
+        let release = release.or(self.release.as_deref()).ok_or_else(|| {
+            Error::from(format!(
+                "Location of source '{}' contains {}, but no release was given. Use option \
+                --release or '{}@<release>'.", self.name, RELEASE_PLACEHOLDER, self.name
+            ))
+        })?;
+        let mut source = self.clone();
+        source.location = self.location.with_release(release);
+        source.release = Some(release.to_string());
+        Ok(source)
+    }
+    pub(crate) fn pin_release(&self, release: &str) -> Result<SourceDef, Error> {
+        if !self.location.needs_release() {
+            return Err(Error::from(format!(
+                "Cannot pick release '{}' for source '{}', since its location {} does not \
+                contain {}.", release, self.name, self.location, RELEASE_PLACEHOLDER
+            )));
+        }
+        self.resolve_release(Some(release))
+    }
+    pub(crate) fn release_label(&self) -> &str {
+        match &self.release {
+            Some(release) => release,
+            None if self.location.needs_release() => "unresolved",
+            None => "unversioned",
         }
     }
 }
 
-impl TryFrom<&str> for Source {
-    type Error = Error;
+fn parse_location(value: Option<&Value>) -> Result<Location, Error> {
+    match value {
+        Some(Value::String(string)) => Location::parse(string),
+        Some(Value::Object(map)) => {
+            if map.contains_key(keys::PATH) {
+                Ok(Location::Local(PathBuf::from(json::get_string(map, keys::PATH)?)))
+            } else {
+                let bucket = json::get_string(map, keys::BUCKET)?;
+                let key = json::get_string_fallback(map, keys::KEY, keys::PREFIX)?;
+                Ok(Location::S3(S3Uri::new(bucket, key)))
+            }
+        }
+        Some(value) => Err(Error::from(format!(
+            "Expected location string or object, but got {}", value
+        ))),
+        None => Err(Error::from(format!("Missing field '{}'", keys::LOCATION))),
+    }
+}
 
-    fn try_from(string: &str) -> Result<Self, Self::Error> {
-        match string {
-            names::GTEX_TSTAT => Ok(Source::GtexTstat),
-            names::GTEX_SLSDC => Ok(Source::GtexSldsc),
-            names::FOURDN_GENE_BIO => Ok(Source::FourDnGeneBio),
-            names::EXRNA_GENE_COUNTS => Ok(Source::ExRnaGeneCounts),
-            _ => Err(Error::from(
-                format!("Unknown source '{}'. Known sources are '{}'.", string,
-                        ALL_SOURCES.map(|source| source.to_string()).join("', '"))
-            ))
+impl Registry {
+    pub(crate) fn builtin() -> Result<Registry, Error> {
+        let sources =
+            sources::ALL.iter().map(|(name, source)| source.to_source_def(name))
+                .collect::<Result<Vec<SourceDef>, Error>>()?;
+        let mut presets = BTreeMap::new();
+        presets.insert(selections::THREE.to_string(), vec![
+            names::GTEX_TSTAT.to_string(),
+            names::GTEX_SLSDC.to_string(),
+            names::FOURDN_GENE_BIO.to_string(),
+        ]);
+        presets.insert(selections::NOVARS.to_string(), vec![
+            exclude_assertion(names::FOURDN_GENE_BIO, four_dn::assertions::SNP_GENE),
+            exclude_assertion(names::FOURDN_GENE_BIO, four_dn::assertions::SNP_DISEASE),
+        ]);
+        Ok(Registry { sources, presets })
+    }
+    pub(crate) fn load() -> Result<Registry, Error> {
+        let mut registry = Registry::builtin()?;
+        if let Some(file) = sources_file()? {
+            registry.add_from_file(&file)?;
         }
+        registry.check_presets()?;
+        Ok(registry)
+    }
+    fn add_from_file(&mut self, file: &PathBuf) -> Result<(), Error> {
+        let reader = BufReader::new(io::open_file(file)?);
+        let value: Value = serde_json::from_reader(reader).map_err(|error| {
+            Error::wrap(format!("Could not parse {}", file.to_string_lossy()), error)
+        })?;
+        let entries = match value.get(keys::SOURCES) {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err(Error::from(format!(
+                "Expected array '{}' in {}", keys::SOURCES, file.to_string_lossy()
+            ))),
+        };
+        match value.get(keys::PRESETS) {
+            Some(Value::Object(presets)) => {
+                for name in presets.keys() {
+                    self.presets.insert(name.clone(), json::get_strings(presets, name)?);
+                }
+            }
+            Some(presets) => {
+                return Err(Error::from(format!(
+                    "Expected object '{}' in {}, but got {}", keys::PRESETS,
+                    file.to_string_lossy(), presets
+                )));
+            }
+            None => {}
+        }
+        for entry in entries {
+            let map = entry.as_object().ok_or_else(|| {
+                Error::from(format!("Expected source object, but got {}", entry))
+            })?;
+            self.add(SourceDef::from_json(map)?);
+        }
+        Ok(())
+    }
+    fn add(&mut self, source: SourceDef) {
+        match self.sources.iter_mut().find(|existing| existing.name == source.name) {
+            Some(existing) => *existing = source,
+            None => self.sources.push(source),
+        }
+    }
+    fn check_presets(&self) -> Result<(), Error> {
+        for name in self.presets.keys() {
+            if name == selections::ALL || self.sources.iter().any(|source| &source.name == name)
+            {
+                return Err(Error::from(format!(
+                    "Preset '{}' has the same name as a source or '{}'.", name, selections::ALL
+                )));
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn sources(&self) -> &[SourceDef] { &self.sources }
+    pub(crate) fn preset_names(&self) -> Vec<&str> {
+        self.presets.keys().map(|name| name.as_str()).collect()
+    }
+    pub(crate) fn names(&self) -> Vec<&str> {
+        self.sources.iter().map(|source| source.name.as_str()).collect()
+    }
+    pub(crate) fn get_with_release(&self, name_release: &str) -> Result<SourceDef, Error> {
+        match name_release.split_once('@') {
+            Some((name, release)) => self.get(name)?.pin_release(release),
+            None => Ok(self.get(name_release)?.clone()),
+        }
+    }
+    pub(crate) fn get(&self, name: &str) -> Result<&SourceDef, Error> {
+        self.sources.iter().find(|source| source.name == name).ok_or_else(|| {
+            Error::from(format!("Unknown source '{}'. Known sources are '{}'.", name,
+                                self.names().join("', '")))
+        })
     }
 }
 
-pub(crate) fn get_data_location(input: &str) -> Result<S3Uri, Error> {
+fn sources_file() -> Result<Option<PathBuf>, Error> {
+    if let Ok(file) = std::env::var(SOURCES_FILE_ENV) {
+        return Ok(Some(PathBuf::from(file)));
+    }
+    let home_dir =
+        home::home_dir().ok_or_else(|| Error::from("Could not determine home directory"))?;
+    let file = home_dir.join("lembic").join(SOURCES_FILE);
+    Ok(if file.exists() { Some(file) } else { None })
+}
+
+pub(crate) fn get_data_location(registry: &Registry, input: &str)
+    -> Result<DataLocation, Error> {
     if let Some(short_name) = input.strip_prefix('@') {
-        let s3uri = Source::try_from(short_name)?.get_s3uri();
-        Ok(s3uri)
+        Ok(registry.get_with_release(short_name)?.resolve_release(None)?.location)
     } else {
-        S3Uri::try_from(input)
+        DataLocation::parse(input)
     }
 }
 
-pub(crate) fn list_sources() {
-    for source in ALL_SOURCES {
-        println!("{}: {}", source, source.get_s3uri());
+pub(crate) fn list_sources(registry: &Registry, release: Option<&str>) {
+    for source in registry.sources() {
+        let source = source.resolve_release(release).unwrap_or_else(|_| source.clone());
+        println!("{}: {} [{}, release {}, assertions {}, confidence {}] {}", source.name,
+                 source.location, source.distiller.name(), source.release_label(),
+                 source.distiller.assertion_types().join("|"),
+                 source.confidence.as_ref().map_or("none".to_string(), |c| c.to_string()),
+                 source.description);
+    }
+    for (name, items) in &registry.presets {
+        println!("{}: preset {}", name, items.join(","));
     }
 }
 
 impl Selection {
-    pub(crate) fn new() -> Selection {
-        Selection {
-            sources: Vec::new(),
-            with_variants: true,
+    pub(crate) fn parse(arg: &str, registry: &Registry) -> Result<Selection, Error> {
+        let mut items: Vec<String> = Vec::new();
+        let arg_items: Vec<&str> = arg.split(',').collect();
+        expand_items(registry, &arg_items, &mut Vec::new(), &mut items)?;
+        let (exclusions, inclusions): (Vec<&String>, Vec<&String>) =
+            items.iter().partition(|item| item.starts_with(selections::EXCLUDE));
+        let mut selection = Selection { sources: Vec::new() };
+        for item in inclusions {
+            selection.include(registry, item)?;
+        }
+        for item in exclusions {
+            selection.exclude(registry, &item[1..])?;
+        }
+        selection.sources.retain(|selected| !selected.assertions.is_empty());
+        Ok(selection)
+    }
+    fn include(&mut self, registry: &Registry, item: &str) -> Result<(), Error> {
+        let (name_release, assertion) = split_assertion(item);
+        let source = registry.get_with_release(name_release)?;
+        let assertions = match assertion {
+            Some(assertion) => vec![check_assertion(&source, assertion)?.to_string()],
+            None => {
+                source.distiller.assertion_types().iter().map(|name| name.to_string()).collect()
+            }
+        };
+        let pinned = name_release.contains('@');
+        match self.sources.iter_mut().find(|selected| selected.source.name == source.name) {
+            Some(selected) => {
+                if pinned {
+                    selected.source = source;
+                }
+                selected.assertions.extend(assertions);
+            }
+            None => {
+                let assertions = assertions.into_iter().collect();
+                self.sources.push(SelectedSource { source, assertions });
+            }
+        }
+        Ok(())
+    }
+    /// A release is checked like for inclusion, but the source is excluded whatever its
+    /// release.
+    fn exclude(&mut self, registry: &Registry, item: &str) -> Result<(), Error> {
+        let (name_release, assertion) = split_assertion(item);
+        let source = registry.get_with_release(name_release)?;
+        let name = source.name.as_str();
+        match assertion {
+            Some(assertion) => {
+                check_assertion(&source, assertion)?;
+                for selected in self.sources.iter_mut() {
+                    if selected.source.name == name {
+                        selected.assertions.remove(assertion);
+                    }
+                }
+            }
+            None => self.sources.retain(|selected| selected.source.name != name),
+        }
+        Ok(())
+    }
+    pub(crate) fn resolve_releases(&mut self, release: Option<&str>) -> Result<(), Error> {
+        for selected in self.sources.iter_mut() {
+            selected.source = selected.source.resolve_release(release)?;
+        }
+        Ok(())
+    }
+}
+
+fn expand_items<'a>(registry: &'a Registry, items: &[&str], presets: &mut Vec<&'a str>,
+                    expanded: &mut Vec<String>) -> Result<(), Error> {
+    for item in items {
+        if *item == selections::ALL {
+            expanded.extend(registry.names().iter().map(|name| name.to_string()));
+        } else if let Some((preset, preset_items)) = registry.presets.get_key_value(*item) {
+            if presets.contains(&preset.as_str()) {
+                return Err(Error::from(format!("Preset '{}' includes itself.", preset)));
+            }
+            presets.push(preset);
+            let preset_items: Vec<&str> = preset_items.iter().map(String::as_str).collect();
+            expand_items(registry, &preset_items, presets, expanded)?;
+            presets.pop();
+        } else {
+            expanded.push(item.to_string());
         }
     }
-    pub(crate) fn add_source(&mut self, source: Source) {
-        self.sources.push(source);
+    Ok(())
+}
+
+fn split_assertion(item: &str) -> (&str, Option<&str>) {
+    match item.split_once(selections::ASSERTION) {
+        Some((name, assertion)) => (name, Some(assertion)),
+        None => (item, None),
+    }
+}
+
+fn check_assertion<'a>(source: &SourceDef, assertion: &'a str) -> Result<&'a str, Error> {
+    let assertion_types = source.distiller.assertion_types();
+    if assertion_types.contains(&assertion) {
+        Ok(assertion)
+    } else {
+        Err(Error::from(format!(
+            "Unknown assertion type '{}' for source '{}'. Known assertion types are '{}'.",
+            assertion, source.name, assertion_types.join("', '")
+        )))
+    }
+}
+
+fn exclude_assertion(name: &str, assertion: &str) -> String {
+    format!("{}{}{}{}", selections::EXCLUDE, name, selections::ASSERTION, assertion)
+}
+
+pub(crate) fn selections_help(registry: &Registry) -> String {
+    format!("Provide comma-separated list from sources '{}', presets '{}' and '{}'. Prefix \
+            with '{}' to exclude, append '{}<assertion>' to pick an assertion type, and pick \
+            a release like '<source>@<release>'", registry.names().join("', '"),
+            registry.preset_names().join("', '"), selections::ALL, selections::EXCLUDE,
+            selections::ASSERTION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn registry() -> Registry {
+        let mut registry = Registry::builtin().unwrap();
+        let source = json!({
+            "name": "local", "location": "/data/{release}/tstat.jsonl", "distiller": "gtex_tstat"
+        });
+        registry.add(SourceDef::from_json(source.as_object().unwrap()).unwrap());
+        registry
     }
-    pub(crate) fn no_variants(&mut self) {
-        self.with_variants = false;
+
+    fn selected_names(selection: &Selection) -> Vec<&str> {
+        selection.sources.iter().map(|selected| selected.source.name.as_str()).collect()
     }
-    pub(crate) fn three_sources(&mut self) {
-        self.sources = vec![Source::GtexTstat, Source::GtexSldsc, Source::FourDnGeneBio];
+
+    #[test]
+    fn exclusion_accepts_release() {
+        let registry = registry();
+        let selection = Selection::parse("local@v8,4dn_gene_bio,-local@v8", &registry).unwrap();
+        assert_eq!(selected_names(&selection), vec!["4dn_gene_bio"]);
+        let selection = Selection::parse("local@v8,4dn_gene_bio,-local", &registry).unwrap();
+        assert_eq!(selected_names(&selection), vec!["4dn_gene_bio"]);
     }
-    pub(crate) fn all_sources(&mut self) {
-        self.sources = ALL_SOURCES.to_vec();
+
+    #[test]
+    fn exclusion_checks_release_and_assertion() {
+        let registry = registry();
+        assert!(Selection::parse("local@v8,-local@v8:nonsense", &registry).is_err());
+        assert!(Selection::parse("4dn_gene_bio,-4dn_gene_bio@v8", &registry).is_err());
+        assert!(Selection::parse("local@v8,-nonsense@v8", &registry).is_err());
     }
-}
\ No newline at end of file
+}
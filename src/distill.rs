@@ -1,120 +1,226 @@
+mod aggregation;
+pub(crate) mod confidence;
+mod correction;
+pub(crate) mod distiller;
+pub(crate) mod evidence;
 mod ex_rna;
-mod four_dn;
+pub(crate) mod four_dn;
+pub(crate) mod gates;
+mod generic;
 mod gtex_sldsc;
-pub(crate) mod gtex_tstat;
+mod gtex_tstat;
 mod mappers;
+pub(crate) mod report;
 mod util;
-mod write;
+pub(crate) mod write;
 
-use crate::data::{Selection, Source};
+use crate::data::{SelectedSource, Selection};
+use crate::distill::confidence::ScoringWriter;
+use crate::distill::distiller::DistillContext;
+use crate::distill::evidence::EvidenceSelection;
+use crate::distill::gates::QualityGates;
+use crate::distill::report::RunReport;
+use crate::distill::write::count::CountingWriter;
+use crate::distill::write::ddkg;
+use crate::distill::write::merge::{self, MergePolicy};
 use crate::distill::write::turtle::TurtleWriter;
-use crate::distill::write::GraphWriter;
+use crate::distill::write::validate::ValidatingWriter;
+use crate::distill::write::{GraphWriter, PredicateStyle};
 use crate::error::Error;
+use crate::location;
 use crate::mapper::hgnc::{GeneMapper, ProteinMapper};
 use crate::mapper::tissues::TissueMapper;
 use crate::mapper::track::Tracker;
 use crate::runtime::Runtime;
 use crate::vocabs::Concepts;
 use penyu::model::iri::Iri;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use crate::distill::write::ddkg::DdkgWriter;
 use crate::mapper::variants::VariantMapper;
 
 pub(crate) fn report_stats(runtime: &Runtime, selection: &Selection) -> Result<(), Error> {
     let mut n_assertions: usize = 0;
-    for source in &selection.sources {
-        n_assertions += match source {
-            Source::GtexTstat => gtex_tstat::report_gtex_tstat(runtime),
-            Source::GtexSldsc => gtex_sldsc::report_gtex_sldsc(runtime),
-            Source::FourDnGeneBio => four_dn::report_four_dn(runtime),
-            Source::ExRnaGeneCounts => ex_rna::report_ex_rna(runtime),
-        }?;
+    for SelectedSource { source, assertions } in &selection.sources {
+        n_assertions += source.distiller.report_stats(runtime, source, assertions)?;
     }
     println!("Total assertions across selected data: {}", n_assertions);
     Ok(())
 }
-pub(crate) fn print_turtle(runtime: &Runtime, selection: &Selection) -> Result<(), Error> {
-    let mut turtle_writer = TurtleWriter::new();
-    output_graph(runtime, selection, &mut turtle_writer)
+pub(crate) struct OutputOptions {
+    pub(crate) evidence: EvidenceSelection,
+    pub(crate) provenance: bool,
+    pub(crate) predicates: PredicateStyle,
+    pub(crate) strict: bool,
+    pub(crate) merge: MergePolicy,
+    pub(crate) dry_run: bool,
+    pub(crate) report: Option<PathBuf>,
+    pub(crate) gates: QualityGates,
+}
+
+impl OutputOptions {
+    pub(crate) fn new() -> OutputOptions {
+        OutputOptions {
+            evidence: EvidenceSelection::None,
+            provenance: false,
+            predicates: PredicateStyle::Curie,
+            strict: false,
+            merge: MergePolicy::Best,
+            dry_run: false,
+            report: None,
+            gates: QualityGates::new(),
+        }
+    }
+}
+
+pub(crate) fn print_turtle(runtime: &Runtime, selection: &Selection, options: &OutputOptions)
+    -> Result<(), Error> {
+    let report_path = options.report.as_deref();
+    if options.dry_run {
+        return output_graph(runtime, selection, &mut CountingWriter::new(), options, report_path);
+    }
+    let mut turtle_writer = TurtleWriter::new(options);
+    output_graph(runtime, selection, &mut turtle_writer, options, report_path)
 }
 
 pub(crate) fn export_ubkg(
     runtime: &Runtime,
     path: &Path,
     selection: &Selection,
+    options: &OutputOptions,
 ) -> Result<(), Error> {
-    let mut writer = DdkgWriter::new(path.to_path_buf());
-    output_graph(runtime, selection, &mut writer)
+    if options.dry_run {
+        let report_path = options.report.as_deref();
+        return output_graph(runtime, selection, &mut CountingWriter::new(), options, report_path);
+    }
+    let report_path = options.report.clone().unwrap_or_else(|| path.join(report::REPORT_FILE));
+    let mut writer = DdkgWriter::new(path.to_path_buf(), options);
+    output_graph(runtime, selection, &mut writer, options, Some(&report_path))
 }
 
-fn output_graph<W: GraphWriter>(runtime: &Runtime, selection: &Selection, writer: &mut W)
-                                -> Result<(), Error> {
+fn output_graph<W: GraphWriter>(runtime: &Runtime, selection: &Selection, writer: &mut W,
+                                options: &OutputOptions, report_path: Option<&Path>)
+    -> Result<(), Error> {
+    let start = Instant::now();
+    let mut report = RunReport::new();
     let mappers_chest = mappers::MappersChest::new()?;
-    let mut tissue_tracker = Tracker::new("tissues".to_string());
-    let mut gene_tracker = Tracker::new("genes".to_string());
-    let mut protein_tracker = Tracker::new("proteins".to_string());
-    let mut variant_tracker = Tracker::new("variants".to_string());
-    for source in &selection.sources {
-        match source {
-            Source::GtexTstat => {
-                let tissue_mapper = mappers_chest.get_tissue_mapper()?;
-                let gene_mapper = mappers_chest.get_gene_mapper()?;
-                gtex_tstat::add_triples_gtex_tstat(
-                    writer,
-                    runtime,
-                    gene_mapper,
-                    tissue_mapper,
-                    &mut gene_tracker,
-                    &mut tissue_tracker,
-                )?;
-            }
-            Source::GtexSldsc => {
-                let tissue_mapper = mappers_chest.get_tissue_mapper()?;
-                gtex_sldsc::add_triples_gtex_sldsc(
-                    writer,
-                    runtime,
-                    tissue_mapper,
-                    &mut tissue_tracker,
-                )?;
-            }
-            Source::FourDnGeneBio => {
-                let gene_mapper = mappers_chest.get_gene_mapper()?;
-                let variant_mapper = mappers_chest.get_variant_mapper()?;
-                four_dn::add_triples_four_dn(
-                    writer,
-                    runtime,
-                    gene_mapper,
-                    variant_mapper,
-                    &mut gene_tracker,
-                    &mut variant_tracker,
-                    selection.with_variants
-                )?;
-            }
-            Source::ExRnaGeneCounts => {
-                let gene_mapper = mappers_chest.get_gene_mapper()?;
-                let protein_mapper = mappers_chest.get_protein_mapper()?;
-                ex_rna::add_triples_ex_rna(
-                    writer,
-                    runtime,
-                    gene_mapper,
-                    protein_mapper,
-                    &mut gene_tracker,
-                    &mut protein_tracker,
-                )?;
-            }
+    for SelectedSource { source, .. } in &selection.sources {
+        for mapper_kind in source.distiller.required_mappers() {
+            mappers_chest.load(mapper_kind)?;
         }
     }
-    if tissue_tracker.any_notes() {
-        eprintln!("{}", tissue_tracker.report());
+    let mut writer = ValidatingWriter::new(writer);
+    let mut trackers = Trackers::new();
+    for SelectedSource { source, assertions } in &selection.sources {
+        let mut context = DistillContext {
+            mappers_chest: &mappers_chest,
+            trackers: &mut trackers,
+            assertions,
+            source: &source.name,
+        };
+        let objects = if options.provenance {
+            location::object_infos(runtime, &source.location)?
+        } else {
+            Vec::new()
+        };
+        writer.add_source(source, &objects);
+        let source_start = Instant::now();
+        let mut scoring_writer = ScoringWriter::new(&mut writer, source.confidence.as_deref());
+        let counts =
+            source.distiller.add_triples(runtime, source, &mut scoring_writer, &mut context)?;
+        let n_emitted = scoring_writer.finish();
+        report.add_source(source, counts, n_emitted, source_start.elapsed());
     }
-    if gene_tracker.any_notes() {
-        eprintln!("{}", gene_tracker.report());
+    trackers.report();
+    for tracker in trackers.all() {
+        report.add_tracker(tracker);
     }
-    if protein_tracker.any_notes() {
-        eprintln!("{}", protein_tracker.report());
+    let ontology_labels: Vec<(Iri, &str)> = writer.nodes().filter_map(|node| {
+        mappers_chest.ontology_label(node).map(|label| (node.clone(), label))
+    }).collect();
+    for (node, label) in ontology_labels {
+        writer.add_ontology_label(&node, label);
+    }
+    let label_conflicts = writer.label_conflicts();
+    label_conflicts.report();
+    report.set_label_conflicts(label_conflicts);
+    let (n_shared_edges, n_edges) = merge::count_shared_edges(writer.edge_sources());
+    eprintln!("{} of {} edges are supported by more than one source.", n_shared_edges, n_edges);
+    let validation_error = match writer.validate(options.strict) {
+        Ok(warnings) => {
+            report.add_warnings(warnings);
+            None
+        }
+        Err(error) => {
+            report.add_warnings(error.to_string().lines().map(String::from).collect());
+            Some(error)
+        }
+    };
+    let n_nodes_without_id =
+        writer.nodes().filter(|node| ddkg::node_iri_to_id(node).is_none()).count();
+    report.set_graph_counts(writer.node_counts(), writer.edge_counts(), n_nodes_without_id,
+                            n_shared_edges);
+    let gate_failures = options.gates.check(&trackers.all());
+    report.add_warnings(gate_failures.clone());
+    let failure = match validation_error {
+        Some(error) => Some(error),
+        None if !gate_failures.is_empty() => Some(Error::from(format!(
+            "{} of {} quality gates failed, so no graph was written:\n{}", gate_failures.len(),
+            options.gates.n_gates(), gate_failures.join("\n")
+        ))),
+        None => None,
+    };
+    if failure.is_none() || !writer.produces_output() {
+        writer.serialize()?;
+    }
+    if let Some(report_path) = report_path {
+        report.set_duration(start.elapsed());
+        match (report.write(report_path), &failure) {
+            (Err(error), None) => return Err(error),
+            (Err(error), Some(_)) => eprintln!("{}", error),
+            (Ok(()), _) => {}
+        }
+    }
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+pub(crate) mod tracker_names {
+    pub(crate) const TISSUES: &str = "tissues";
+    pub(crate) const GENES: &str = "genes";
+    pub(crate) const PROTEINS: &str = "proteins";
+    pub(crate) const VARIANTS: &str = "variants";
+    pub(crate) const ALL: [&str; 4] = [TISSUES, GENES, PROTEINS, VARIANTS];
+}
+
+pub(crate) struct Trackers {
+    pub(crate) tissue: Tracker,
+    pub(crate) gene: Tracker,
+    pub(crate) protein: Tracker,
+    pub(crate) variant: Tracker,
+}
+
+impl Trackers {
+    fn new() -> Trackers {
+        Trackers {
+            tissue: Tracker::new(tracker_names::TISSUES.to_string()),
+            gene: Tracker::new(tracker_names::GENES.to_string()),
+            protein: Tracker::new(tracker_names::PROTEINS.to_string()),
+            variant: Tracker::new(tracker_names::VARIANTS.to_string()),
+        }
+    }
+    fn all(&self) -> [&Tracker; 4] {
+        [&self.tissue, &self.gene, &self.protein, &self.variant]
+    }
+    fn report(&self) {
+        for tracker in self.all() {
+            if tracker.any_notes() {
+                eprintln!("{}", tracker.report());
+            }
+        }
     }
-    writer.serialize()?;
-    Ok(())
 }
 
 fn get_tissue_iri(tissue_mapper: &TissueMapper, tissue: &str, tracker: &mut Tracker) -> Iri {
@@ -0,0 +1,55 @@
+use crate::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Aggregation {
+    Max,
+    Sum,
+    NoisyOr,
+}
+
+mod aggregations {
+    pub(crate) const MAX: &str = "max";
+    pub(crate) const SUM: &str = "sum";
+    pub(crate) const NOISY_OR: &str = "noisy_or";
+    pub(crate) const ALL: [&str; 3] = [MAX, SUM, NOISY_OR];
+}
+
+impl TryFrom<&str> for Aggregation {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            aggregations::MAX => Ok(Aggregation::Max),
+            aggregations::SUM => Ok(Aggregation::Sum),
+            aggregations::NOISY_OR => Ok(Aggregation::NoisyOr),
+            _ => Err(Error::from(format!(
+                "Unknown aggregation '{}'. Known aggregations are '{}'.", string,
+                aggregations::ALL.join("', '")
+            ))),
+        }
+    }
+}
+
+impl Display for Aggregation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregation::Max => write!(f, "{}", aggregations::MAX),
+            Aggregation::Sum => write!(f, "{}", aggregations::SUM),
+            Aggregation::NoisyOr => write!(f, "{}", aggregations::NOISY_OR),
+        }
+    }
+}
+
+impl Aggregation {
+    pub(crate) fn aggregate<I: IntoIterator<Item = f64>>(&self, values: I) -> f64 {
+        let values = values.into_iter();
+        match self {
+            Aggregation::Max => values.fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Sum => values.sum(),
+            Aggregation::NoisyOr => {
+                1.0 - values.map(|value| 1.0 - value.clamp(0.0, 1.0)).product::<f64>()
+            }
+        }
+    }
+}
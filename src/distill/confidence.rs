@@ -0,0 +1,269 @@
+use crate::data::SourceDef;
+use crate::distill::evidence::{Evidence, EvidenceValue, ScoreDirection};
+use crate::distill::write::labels::LabelConflicts;
+use crate::distill::write::GraphWriter;
+use crate::error::Error;
+use crate::expr::{as_number, Parser, ValueExpr};
+use crate::json;
+use crate::location::ObjectInfo;
+use crate::vocabs::Predicates;
+use penyu::model::iri::Iri;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+pub(crate) enum Confidence {
+    Percentile,
+    Logistic { midpoint: f64, scale: f64 },
+    Formula(String, ValueExpr),
+}
+
+mod methods {
+    pub(crate) const PERCENTILE: &str = "percentile";
+    pub(crate) const LOGISTIC: &str = "logistic";
+    pub(crate) const FORMULA: &str = "formula";
+    pub(crate) const ALL: [&str; 3] = [PERCENTILE, LOGISTIC, FORMULA];
+}
+
+mod keys {
+    pub(crate) const METHOD: &str = "method";
+    pub(crate) const MIDPOINT: &str = "midpoint";
+    pub(crate) const SCALE: &str = "scale";
+    pub(crate) const FORMULA: &str = "formula";
+}
+
+const DEFAULT_MIDPOINT: f64 = 0.0;
+const DEFAULT_SCALE: f64 = 1.0;
+
+/// Buffers the edges of one source, so that confidence can be assigned relative to all of them.
+/// Without a confidence method, edges are passed through unchanged.
+pub(crate) struct ScoringWriter<'a, W: GraphWriter> {
+    inner: &'a mut W,
+    confidence: Option<&'a Confidence>,
+    triples: Vec<(Iri, Predicates, Iri)>,
+    evidences: Vec<Evidence>,
+    n_passed: usize,
+}
+
+impl Confidence {
+    pub(crate) fn from_json(value: &Value) -> Result<Confidence, Error> {
+        match value {
+            Value::String(method) => Confidence::from_method(method, &Map::new()),
+            Value::Object(map) => {
+                let method = json::get_string(map, keys::METHOD)?;
+                Confidence::from_method(&method, map)
+            }
+            _ => Err(Error::from(format!(
+                "Expected confidence method or object with field '{}', but got {}",
+                keys::METHOD, value
+            ))),
+        }
+    }
+    fn from_method(method: &str, map: &Map<String, Value>) -> Result<Confidence, Error> {
+        match method {
+            methods::PERCENTILE => Ok(Confidence::Percentile),
+            methods::LOGISTIC => {
+                let midpoint = match map.get(keys::MIDPOINT) {
+                    Some(_) => json::get_number(map, keys::MIDPOINT)?,
+                    None => DEFAULT_MIDPOINT,
+                };
+                let scale = match map.get(keys::SCALE) {
+                    Some(_) => json::get_number(map, keys::SCALE)?,
+                    None => DEFAULT_SCALE,
+                };
+                if scale <= 0.0 {
+                    return Err(Error::from(format!(
+                        "Field '{}' needs to be positive, but is {}.", keys::SCALE, scale
+                    )));
+                }
+                Ok(Confidence::Logistic { midpoint, scale })
+            }
+            methods::FORMULA => {
+                let formula = json::get_string(map, keys::FORMULA)?;
+                let mut parser = Parser::new(&formula)?;
+                let expr = parser.parse_value()?;
+                parser.expect_end()?;
+                Ok(Confidence::Formula(formula, expr))
+            }
+            _ => Err(Error::from(format!(
+                "Unknown confidence method '{}'. Known confidence methods are '{}'.", method,
+                methods::ALL.join("', '")
+            ))),
+        }
+    }
+    /// Assigns confidence to each evidence, where `predicates` holds the predicate of each edge.
+    /// Percentiles are ranked per predicate, so that e.g. over- and under-expression are not
+    /// mixed.
+    pub(crate) fn assign(&self, predicates: &[Predicates], evidences: &mut [Evidence]) {
+        match self {
+            Confidence::Percentile => {
+                let mut values_by_predicate: BTreeMap<Predicates, Vec<f64>> = BTreeMap::new();
+                for (predicate, evidence) in predicates.iter().zip(evidences.iter()) {
+                    if let Some(value) = directed_score(evidence) {
+                        values_by_predicate.entry(*predicate).or_default().push(value);
+                    }
+                }
+                for values in values_by_predicate.values_mut() {
+                    values.sort_by(f64::total_cmp);
+                }
+                for (predicate, evidence) in predicates.iter().zip(evidences.iter_mut()) {
+                    if let Some(value) = directed_score(evidence)
+                        && let Some(values) = values_by_predicate.get(predicate) {
+                        let n_at_most = values.partition_point(|other| *other <= value);
+                        evidence.set_confidence((n_at_most as f64) / (values.len() as f64));
+                    }
+                }
+            }
+            Confidence::Logistic { midpoint, scale } => {
+                for evidence in evidences.iter_mut() {
+                    if let (Some(value), Some((_, direction))) =
+                        (score_value(evidence), evidence.score()) {
+                        let x = match direction {
+                            ScoreDirection::HigherIsBetter => (value - midpoint) / scale,
+                            ScoreDirection::LowerIsBetter => (midpoint - value) / scale,
+                        };
+                        evidence.set_confidence(1.0 / (1.0 + (-x).exp()));
+                    }
+                }
+            }
+            Confidence::Formula(_, expr) => {
+                for evidence in evidences.iter_mut() {
+                    let map = properties_map(evidence);
+                    if let Some(value) = as_number(&expr.evaluate(&map))
+                        && !value.is_nan() {
+                        evidence.set_confidence(value.clamp(0.0, 1.0));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Display for Confidence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Confidence::Percentile => write!(f, "{}", methods::PERCENTILE),
+            Confidence::Logistic { midpoint, scale } => {
+                write!(f, "{} (midpoint {}, scale {})", methods::LOGISTIC, midpoint, scale)
+            }
+            Confidence::Formula(formula, _) => write!(f, "{} {}", methods::FORMULA, formula),
+        }
+    }
+}
+
+fn score_value(evidence: &Evidence) -> Option<f64> {
+    evidence.score_value().filter(|value| !value.is_nan())
+}
+
+/// The score, negated if lower is better, so that higher is always better.
+fn directed_score(evidence: &Evidence) -> Option<f64> {
+    let value = score_value(evidence)?;
+    match evidence.score()? {
+        (_, ScoreDirection::HigherIsBetter) => Some(value),
+        (_, ScoreDirection::LowerIsBetter) => Some(-value),
+    }
+}
+
+fn properties_map(evidence: &Evidence) -> Map<String, Value> {
+    evidence.properties().iter().map(|(name, value)| {
+        let value = match value {
+            EvidenceValue::Number(number) => Value::from(*number),
+            EvidenceValue::Integer(integer) => Value::from(*integer),
+            EvidenceValue::Text(text) => Value::String(text.clone()),
+        };
+        (name.clone(), value)
+    }).collect()
+}
+
+impl<'a, W: GraphWriter> ScoringWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W, confidence: Option<&'a Confidence>)
+        -> ScoringWriter<'a, W> {
+        ScoringWriter {
+            inner, confidence, triples: Vec::new(), evidences: Vec::new(), n_passed: 0
+        }
+    }
+    /// Assigns confidence and forwards the buffered edges, returning how many edges there were.
+    pub(crate) fn finish(self) -> usize {
+        let ScoringWriter { inner, confidence, triples, mut evidences, n_passed } = self;
+        if let Some(confidence) = confidence {
+            let predicates: Vec<Predicates> =
+                triples.iter().map(|(_, predicate, _)| *predicate).collect();
+            confidence.assign(&predicates, &mut evidences);
+        }
+        for ((subject, predicate, object), evidence) in triples.iter().zip(&evidences) {
+            inner.add_edge(subject, *predicate, object, evidence);
+        }
+        n_passed + triples.len()
+    }
+}
+
+impl<W: GraphWriter> GraphWriter for ScoringWriter<'_, W> {
+    fn add_node(&mut self, node: &Iri, class: &Iri, label: &str) {
+        self.inner.add_node(node, class, label);
+    }
+    fn add_ontology_label(&mut self, node: &Iri, label: &str) {
+        self.inner.add_ontology_label(node, label);
+    }
+    fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                evidence: &Evidence) {
+        if self.confidence.is_none() {
+            self.inner.add_edge(subject, predicate, object, evidence);
+            self.n_passed += 1;
+            return;
+        }
+        self.triples.push((subject.clone(), predicate, object.clone()));
+        self.evidences.push(evidence.clone());
+    }
+    fn add_source(&mut self, source: &SourceDef, objects: &[ObjectInfo]) {
+        self.inner.add_source(source, objects);
+    }
+    fn label_conflicts(&self) -> LabelConflicts {
+        self.inner.label_conflicts()
+    }
+    fn produces_output(&self) -> bool {
+        self.inner.produces_output()
+    }
+    fn serialize(&mut self) -> Result<(), Error> {
+        self.inner.serialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scored(value: f64, direction: ScoreDirection) -> Evidence {
+        let mut evidence = Evidence::new("test");
+        evidence.add_number("score", value);
+        evidence.set_score("score", direction);
+        evidence
+    }
+
+    #[test]
+    fn percentiles_are_ranked_per_predicate() {
+        let predicates = [
+            Predicates::OverExpressedIn, Predicates::OverExpressedIn,
+            Predicates::UnderExpressedIn, Predicates::UnderExpressedIn,
+        ];
+        let mut evidences = vec![
+            scored(2.0, ScoreDirection::HigherIsBetter),
+            scored(4.0, ScoreDirection::HigherIsBetter),
+            scored(-4.0, ScoreDirection::LowerIsBetter),
+            scored(-2.0, ScoreDirection::LowerIsBetter),
+        ];
+        Confidence::Percentile.assign(&predicates, &mut evidences);
+        let confidences: Vec<Option<f64>> =
+            evidences.iter().map(Evidence::confidence).collect();
+        assert_eq!(confidences, vec![Some(0.5), Some(1.0), Some(1.0), Some(0.5)]);
+    }
+
+    #[test]
+    fn unscored_evidence_gets_no_percentile() {
+        let predicates = [Predicates::OverExpressedIn, Predicates::OverExpressedIn];
+        let mut evidences =
+            vec![scored(1.0, ScoreDirection::HigherIsBetter), Evidence::new("test")];
+        Confidence::Percentile.assign(&predicates, &mut evidences);
+        assert_eq!(evidences[0].confidence(), Some(1.0));
+        assert_eq!(evidences[1].confidence(), None);
+    }
+}
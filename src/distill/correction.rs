@@ -0,0 +1,103 @@
+use crate::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Correction {
+    None,
+    Bonferroni,
+    BenjaminiHochberg,
+}
+
+mod corrections {
+    pub(crate) const NONE: &str = "none";
+    pub(crate) const BONFERRONI: &str = "bonferroni";
+    pub(crate) const BH: &str = "bh";
+    pub(crate) const ALL: [&str; 3] = [NONE, BONFERRONI, BH];
+}
+
+impl TryFrom<&str> for Correction {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            corrections::NONE => Ok(Correction::None),
+            corrections::BONFERRONI => Ok(Correction::Bonferroni),
+            corrections::BH => Ok(Correction::BenjaminiHochberg),
+            _ => Err(Error::from(format!(
+                "Unknown correction '{}'. Known corrections are '{}'.", string,
+                corrections::ALL.join("', '")
+            ))),
+        }
+    }
+}
+
+impl Display for Correction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Correction::None => write!(f, "no correction"),
+            Correction::Bonferroni => write!(f, "Bonferroni"),
+            Correction::BenjaminiHochberg => write!(f, "Benjamini-Hochberg FDR"),
+        }
+    }
+}
+
+impl Correction {
+    pub(crate) fn adjust(&self, p_values: &[f64]) -> Vec<f64> {
+        let n_tests = p_values.len() as f64;
+        match self {
+            Correction::None => p_values.to_vec(),
+            Correction::Bonferroni => {
+                p_values.iter().map(|p_value| (p_value * n_tests).min(1.0)).collect()
+            }
+            Correction::BenjaminiHochberg => {
+                let mut order: Vec<usize> = (0..p_values.len()).collect();
+                order.sort_by(|&i, &j| p_values[i].total_cmp(&p_values[j]));
+                let mut q_values = vec![1.0; p_values.len()];
+                let mut q_min: f64 = 1.0;
+                for (rank, &i) in order.iter().enumerate().rev() {
+                    q_min = q_min.min(p_values[i] * n_tests / (rank + 1) as f64);
+                    q_values[i] = q_min;
+                }
+                q_values
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn bonferroni_multiplies_by_number_of_tests_and_caps_at_one() {
+        let q_values = Correction::Bonferroni.adjust(&[0.01, 0.3, 0.5]);
+        assert_close(&q_values, &[0.03, 0.9, 1.0]);
+    }
+
+    #[test]
+    fn bh_takes_cumulative_minimum_from_the_largest_p_value_down() {
+        // Sorted: 0.01 -> 0.04, 0.011 -> 0.022, 0.03 -> 0.04, 0.04 -> 0.04, so the
+        // smallest p-value inherits 0.022 from the next rank.
+        let q_values = Correction::BenjaminiHochberg.adjust(&[0.04, 0.01, 0.03, 0.011]);
+        assert_close(&q_values, &[0.04, 0.022, 0.04, 0.022]);
+    }
+
+    #[test]
+    fn bh_gives_tied_p_values_the_same_q_value() {
+        let q_values = Correction::BenjaminiHochberg.adjust(&[0.02, 0.5, 0.02]);
+        assert_close(&q_values, &[0.03, 0.5, 0.03]);
+    }
+
+    #[test]
+    fn no_correction_keeps_p_values() {
+        assert_close(&Correction::None.adjust(&[0.2, 0.01]), &[0.2, 0.01]);
+        assert!(Correction::BenjaminiHochberg.adjust(&[]).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::data::SourceDef;
+use crate::distill::ex_rna::ExRnaDistiller;
+use crate::distill::four_dn::FourDnDistiller;
+use crate::distill::generic::GenericDistiller;
+use crate::distill::gtex_sldsc::GtexSldscDistiller;
+use crate::distill::gtex_tstat::GtexTstatDistiller;
+use crate::distill::mappers::{MapperKind, MappersChest};
+use crate::distill::report::RecordCounts;
+use crate::distill::write::GraphWriter;
+use crate::distill::Trackers;
+use crate::distill::{ex_rna, four_dn, generic, gtex_sldsc, gtex_tstat};
+use crate::error::Error;
+use crate::location;
+use crate::location::DataLocation;
+use crate::pipe::Summary;
+use crate::runtime::Runtime;
+use crate::verify::{LineCheck, LineCheckSummary};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+pub(crate) const NAMES: [&str; 5] =
+    [gtex_tstat::NAME, gtex_sldsc::NAME, four_dn::NAME, ex_rna::NAME, generic::NAME];
+
+pub(crate) struct DistillContext<'a> {
+    pub(crate) mappers_chest: &'a MappersChest,
+    pub(crate) trackers: &'a mut Trackers,
+    pub(crate) assertions: &'a BTreeSet<String>,
+    pub(crate) source: &'a str,
+}
+
+impl DistillContext<'_> {
+    pub(crate) fn includes(&self, assertion: &str) -> bool {
+        self.assertions.contains(assertion)
+    }
+}
+
+pub(crate) trait Distiller {
+    type Summary: Summary;
+    fn name(&self) -> &str;
+    fn assertion_types(&self) -> Vec<&str>;
+    fn required_mappers(&self) -> Vec<MapperKind>;
+    fn new_summary(&self) -> Self::Summary;
+    fn summarize(&self, runtime: &Runtime, location: &DataLocation)
+                 -> Result<Self::Summary, Error>;
+    fn report_stats(&self, source: &SourceDef, summary: Self::Summary,
+                    assertions: &BTreeSet<String>) -> usize;
+    fn count_records(&self, summary: &Self::Summary) -> RecordCounts;
+    fn add_triples(&self, summary: Self::Summary, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<(), Error>;
+}
+
+pub(crate) trait AnyDistiller {
+    fn name(&self) -> &str;
+    fn assertion_types(&self) -> Vec<&str>;
+    fn required_mappers(&self) -> Vec<MapperKind>;
+    fn check_lines(&self, runtime: &Runtime, location: &DataLocation, n_lines: usize)
+                   -> Result<LineCheck, Error>;
+    fn report_stats(&self, runtime: &Runtime, source: &SourceDef,
+                    assertions: &BTreeSet<String>) -> Result<usize, Error>;
+    fn add_triples(&self, runtime: &Runtime, source: &SourceDef, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<RecordCounts, Error>;
+}
+
+impl<D: Distiller> AnyDistiller for D {
+    fn name(&self) -> &str { Distiller::name(self) }
+    fn assertion_types(&self) -> Vec<&str> { Distiller::assertion_types(self) }
+    fn required_mappers(&self) -> Vec<MapperKind> { Distiller::required_mappers(self) }
+    fn check_lines(&self, runtime: &Runtime, location: &DataLocation, n_lines: usize)
+                   -> Result<LineCheck, Error> {
+        let summary = LineCheckSummary::new(self);
+        Ok(location::process_head_from(runtime, location, summary, n_lines)?.check)
+    }
+    fn report_stats(&self, runtime: &Runtime, source: &SourceDef,
+                    assertions: &BTreeSet<String>) -> Result<usize, Error> {
+        let summary = self.summarize(runtime, &source.location)?;
+        Ok(Distiller::report_stats(self, source, summary, assertions))
+    }
+    fn add_triples(&self, runtime: &Runtime, source: &SourceDef, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<RecordCounts, Error> {
+        let summary = self.summarize(runtime, &source.location)?;
+        let counts = self.count_records(&summary);
+        Distiller::add_triples(self, summary, writer, context)?;
+        Ok(counts)
+    }
+}
+
+/// Prints the number of assertions of one type, and returns it only if the type is selected.
+pub(crate) fn report_assertions(assertions: &BTreeSet<String>, assertion: &str,
+                                description: &str, n_assertions: usize) -> usize {
+    if assertions.contains(assertion) {
+        println!("Assertions: {} ({})", description, n_assertions);
+        n_assertions
+    } else {
+        println!("Assertions: {} ({}, excluded)", description, n_assertions);
+        0
+    }
+}
+
+pub(crate) fn create_distiller(name: &str, config: Option<&Map<String, Value>>)
+                               -> Result<Rc<dyn AnyDistiller>, Error> {
+    match name {
+        gtex_tstat::NAME => Ok(Rc::new(GtexTstatDistiller::new(config)?)),
+        gtex_sldsc::NAME => Ok(Rc::new(GtexSldscDistiller::new(config)?)),
+        four_dn::NAME => Ok(Rc::new(FourDnDistiller::new(config)?)),
+        ex_rna::NAME => Ok(Rc::new(ExRnaDistiller::new(config)?)),
+        generic::NAME => {
+            let config = config.ok_or_else(|| {
+                Error::from(format!("Distiller '{}' needs a spec.", generic::NAME))
+            })?;
+            Ok(Rc::new(GenericDistiller::from_json(config)?))
+        }
+        _ => Err(Error::from(format!(
+            "Unknown distiller '{}'. Known distillers are '{}'.", name, NAMES.join("', '")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_selected_assertions_are_counted() {
+        let assertions: BTreeSet<String> = BTreeSet::from(["gene_disease".to_string()]);
+        assert_eq!(report_assertions(&assertions, "gene_disease", "gene - disease", 3), 3);
+        assert_eq!(report_assertions(&assertions, "snp_gene", "snp - gene", 4), 0);
+    }
+}
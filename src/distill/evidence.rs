@@ -0,0 +1,241 @@
+use crate::distill::util::pretty_f64;
+use crate::error::Error;
+use penyu::model::iri::Iri;
+use penyu::model::literal::{Literal, LiteralTag};
+use penyu::vocabs::xsd;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone)]
+pub(crate) enum EvidenceValue {
+    Number(f64),
+    Integer(i64),
+    Text(String),
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ScoreDirection {
+    HigherIsBetter,
+    LowerIsBetter,
+}
+
+/// A source record, as the 1-based index of its object in `source_objects.tsv` and its 1-based
+/// line number within that object, header lines included.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct RecordId {
+    pub(crate) object: usize,
+    pub(crate) line: usize,
+}
+
+#[derive(Clone)]
+pub(crate) struct Evidence {
+    source: String,
+    properties: Vec<(String, EvidenceValue)>,
+    score: Option<(String, ScoreDirection)>,
+    records: Vec<RecordId>,
+    confidence: Option<f64>,
+}
+
+#[derive(Clone)]
+pub(crate) enum EvidenceSelection {
+    None,
+    All,
+    Named(Vec<String>),
+}
+
+mod directions {
+    pub(crate) const HIGHER: &str = "higher";
+    pub(crate) const LOWER: &str = "lower";
+    pub(crate) const ALL: [&str; 2] = [HIGHER, LOWER];
+}
+
+const ALL_PROPERTIES: &str = "all";
+
+impl EvidenceValue {
+    pub(crate) fn datatype(&self) -> &'static Iri {
+        match self {
+            EvidenceValue::Number(_) => xsd::DOUBLE,
+            EvidenceValue::Integer(_) => xsd::INTEGER,
+            EvidenceValue::Text(_) => xsd::STRING,
+        }
+    }
+    pub(crate) fn to_literal(&self) -> Literal {
+        let string = match self {
+            EvidenceValue::Number(number) => number.to_string(),
+            EvidenceValue::Integer(integer) => integer.to_string(),
+            EvidenceValue::Text(text) => text.clone(),
+        };
+        Literal::new(string, LiteralTag::Type(self.datatype().clone()))
+    }
+}
+
+impl Display for EvidenceValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvidenceValue::Number(number) => write!(f, "{}", pretty_f64(*number)),
+            EvidenceValue::Integer(integer) => write!(f, "{}", integer),
+            EvidenceValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl RecordId {
+    /// The position before the first line of the first object.
+    pub(crate) fn new() -> RecordId { RecordId { object: 1, line: 0 } }
+    pub(crate) fn start_object(self, object: usize) -> RecordId { RecordId { object, line: 0 } }
+    pub(crate) fn next_line(self) -> RecordId {
+        RecordId { object: self.object, line: self.line + 1 }
+    }
+}
+
+impl Display for RecordId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.object, self.line)
+    }
+}
+
+impl TryFrom<&str> for ScoreDirection {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            directions::HIGHER => Ok(ScoreDirection::HigherIsBetter),
+            directions::LOWER => Ok(ScoreDirection::LowerIsBetter),
+            _ => Err(Error::from(format!(
+                "Unknown score direction '{}'. Known score directions are '{}'.", string,
+                directions::ALL.join("', '")
+            ))),
+        }
+    }
+}
+
+impl Display for ScoreDirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoreDirection::HigherIsBetter => write!(f, "{}", directions::HIGHER),
+            ScoreDirection::LowerIsBetter => write!(f, "{}", directions::LOWER),
+        }
+    }
+}
+
+impl Evidence {
+    pub(crate) fn new(source: &str) -> Evidence {
+        Evidence {
+            source: source.to_string(),
+            properties: Vec::new(),
+            score: None,
+            records: Vec::new(),
+            confidence: None,
+        }
+    }
+    pub(crate) fn add(&mut self, name: &str, value: EvidenceValue) {
+        self.properties.push((name.to_string(), value));
+    }
+    pub(crate) fn add_number(&mut self, name: &str, number: f64) {
+        self.add(name, EvidenceValue::Number(number));
+    }
+    pub(crate) fn add_integer(&mut self, name: &str, integer: i64) {
+        self.add(name, EvidenceValue::Integer(integer));
+    }
+    pub(crate) fn add_text(&mut self, name: &str, text: &str) {
+        self.add(name, EvidenceValue::Text(text.to_string()));
+    }
+    pub(crate) fn set_score(&mut self, name: &str, direction: ScoreDirection) {
+        self.score = Some((name.to_string(), direction));
+    }
+    pub(crate) fn set_records(&mut self, mut records: Vec<RecordId>) {
+        records.sort();
+        records.dedup();
+        self.records = records;
+    }
+    pub(crate) fn set_confidence(&mut self, confidence: f64) {
+        self.confidence = Some(confidence);
+    }
+    pub(crate) fn source(&self) -> &str { &self.source }
+    pub(crate) fn confidence(&self) -> Option<f64> { self.confidence }
+    pub(crate) fn records(&self) -> &[RecordId] { &self.records }
+    pub(crate) fn properties(&self) -> &[(String, EvidenceValue)] { &self.properties }
+    pub(crate) fn score(&self) -> Option<(&str, ScoreDirection)> {
+        self.score.as_ref().map(|(name, direction)| (name.as_str(), *direction))
+    }
+    pub(crate) fn get(&self, name: &str) -> Option<&EvidenceValue> {
+        self.properties.iter().find(|(property, _)| property == name).map(|(_, value)| value)
+    }
+    pub(crate) fn score_value(&self) -> Option<f64> {
+        let (name, _) = self.score()?;
+        match self.get(name)? {
+            EvidenceValue::Number(number) => Some(*number),
+            EvidenceValue::Integer(integer) => Some(*integer as f64),
+            EvidenceValue::Text(_) => None,
+        }
+    }
+    /// Confidence is comparable across sources, scores only if they have the same name and
+    /// direction. Otherwise any scored evidence beats unscored evidence, and ties keep the
+    /// evidence seen first.
+    pub(crate) fn is_better_than(&self, other: &Evidence) -> bool {
+        if let (Some(confidence), Some(other_confidence)) = (self.confidence, other.confidence) {
+            return confidence > other_confidence;
+        }
+        match (self.score(), other.score()) {
+            (Some((name, direction)), Some((other_name, other_direction)))
+                if name == other_name && direction == other_direction => {
+                match (self.score_value(), other.score_value()) {
+                    (Some(value), Some(other_value)) => match direction {
+                        ScoreDirection::HigherIsBetter => value > other_value,
+                        ScoreDirection::LowerIsBetter => value < other_value,
+                    },
+                    (Some(_), None) => true,
+                    _ => false,
+                }
+            }
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Evidence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let properties: Vec<String> =
+            self.properties.iter().map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+        write!(f, "{}", properties.join(","))
+    }
+}
+
+impl EvidenceSelection {
+    pub(crate) fn parse(string: &str) -> EvidenceSelection {
+        if string == ALL_PROPERTIES {
+            EvidenceSelection::All
+        } else {
+            EvidenceSelection::Named(string.split(',').map(|name| name.to_string()).collect())
+        }
+    }
+    pub(crate) fn is_none(&self) -> bool { matches!(self, EvidenceSelection::None) }
+    pub(crate) fn selects(&self, name: &str) -> bool {
+        match self {
+            EvidenceSelection::None => false,
+            EvidenceSelection::All => true,
+            EvidenceSelection::Named(names) => names.iter().any(|selected| selected == name),
+        }
+    }
+    pub(crate) fn columns<'a, I>(&self, evidences: I) -> Vec<String>
+    where
+        I: Iterator<Item = &'a Evidence>,
+    {
+        match self {
+            EvidenceSelection::None => Vec::new(),
+            EvidenceSelection::Named(names) => names.clone(),
+            EvidenceSelection::All => {
+                let mut columns: Vec<String> = Vec::new();
+                for evidence in evidences {
+                    for (name, _) in evidence.properties() {
+                        if !columns.contains(name) {
+                            columns.push(name.clone());
+                        }
+                    }
+                }
+                columns
+            }
+        }
+    }
+}
@@ -1,37 +1,73 @@
-use crate::data::sources;
+use crate::data::SourceDef;
+use crate::distill::aggregation::Aggregation;
+use crate::distill::distiller::{self, DistillContext, Distiller};
+use crate::distill::evidence::{Evidence, RecordId, ScoreDirection};
+use crate::distill::mappers::MapperKind;
+use crate::distill::report::RecordCounts;
+use crate::distill::util::pretty_f64;
 use crate::distill::write::GraphWriter;
 use crate::error::Error;
-use crate::mapper::hgnc::{GeneMapper, ProteinMapper};
-use crate::mapper::track::Tracker;
+use crate::location::DataLocation;
 use crate::pipe::{LinePipe, NextSummary, Summary};
 use crate::runtime::Runtime;
-use crate::s3::S3Uri;
-use crate::{distill, json, s3, vocabs};
-use std::collections::BTreeSet;
-
-pub(crate) fn report_ex_rna(runtime: &Runtime) -> Result<usize, Error> {
-    println!("From the exRNA gene counts data:");
-    let summary = distill_ex_rna(runtime)?;
-    println!("Original records: {}", summary.n_original);
-    let n_assertions = summary.rbp_genes.len();
-    println!("Assertions: RNA-binding protein - binds RNA - gene ({})", n_assertions);
-    Ok(n_assertions)
-}
-
-pub(crate) fn distill_ex_rna(runtime: &Runtime) -> Result<ExRnaSummary, Error> {
-    let s3uri = sources::EXRNA_GENE_COUNTS.to_s3uri();
-    let pipe = ExRnaPipe::new(s3uri);
-    let summary = s3::process(runtime, &pipe)?;
+use crate::vocabs::Predicates;
+use crate::{distill, json, location, vocabs};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub(crate) const NAME: &str = "exrna_gene_counts";
+
+pub(crate) mod assertions {
+    pub(crate) const RBP_GENE: &str = "rbp_gene";
+}
+
+const COUNTS_PER_MILLION: f64 = 1_000_000.0;
+
+mod evidence_names {
+    pub(crate) const READ_COUNT: &str = "read_count";
+    pub(crate) const AGGREGATION: &str = "aggregation";
+    pub(crate) const CPM: &str = "cpm";
+    pub(crate) const PERCENTILE: &str = "percentile";
+}
+
+mod keys {
+    pub(crate) const AGGREGATION: &str = "aggregation";
+    pub(crate) const MIN_READ_COUNT: &str = "min_read_count";
+    pub(crate) const TOP_K: &str = "top_k";
+}
+
+pub(crate) struct ExRnaDistiller {
+    aggregation: Aggregation,
+    min_read_count: u64,
+    top_k: Option<usize>,
+}
+
+type CountsRecords = (Vec<f64>, Vec<RecordId>);
+
+struct RbpGeneEdge<'a> {
+    rbp: &'a str,
+    gene: &'a str,
+    read_count: u64,
+    cpm: f64,
+    percentile: f64,
+    records: Vec<RecordId>,
+}
+
+pub(crate) fn distill_ex_rna(runtime: &Runtime, location: &DataLocation)
+    -> Result<ExRnaSummary, Error> {
+    let pipe = ExRnaPipe::new(location.clone());
+    let summary = location::process(runtime, &pipe)?;
     Ok(summary)
 }
 
 pub(crate) struct ExRnaPipe {
-    s3uri: S3Uri
+    location: DataLocation
 }
 
 pub(crate) struct ExRnaSummary {
     n_original: usize,
-    rbp_genes: BTreeSet<RbpGene>
+    position: RecordId,
+    rbp_genes: BTreeMap<RbpGene, Vec<RecordId>>
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq)]
@@ -43,7 +79,7 @@ pub(crate) struct RbpGene {
 
 impl ExRnaSummary {
     pub(crate) fn new() -> ExRnaSummary {
-        ExRnaSummary { n_original: 0, rbp_genes: BTreeSet::new() }
+        ExRnaSummary { n_original: 0, position: RecordId::new(), rbp_genes: BTreeMap::new() }
     }
 }
 
@@ -54,42 +90,220 @@ impl Summary for ExRnaSummary {
         let rbp = json::get_string(&json_obj, "rbp")?;
         let read_count = json::get_number(&json_obj, "read_count")?.round() as u64;
         let rbp_gene = RbpGene { rbp, gene, read_count };
-        let ExRnaSummary { mut n_original, mut rbp_genes } = self;
+        let ExRnaSummary { mut n_original, position, mut rbp_genes } = self;
         n_original += 1;
-        rbp_genes.insert(rbp_gene);
-        Ok(NextSummary { summary: ExRnaSummary { n_original, rbp_genes } })
+        let position = position.next_line();
+        rbp_genes.entry(rbp_gene).or_default().push(position);
+        Ok(NextSummary { summary: ExRnaSummary { n_original, position, rbp_genes } })
+    }
+    fn start_object(self, index: usize) -> Self {
+        ExRnaSummary { position: self.position.start_object(index), ..self }
+    }
+    fn skip_line(self) -> Self {
+        ExRnaSummary { position: self.position.next_line(), ..self }
     }
 }
 
 impl ExRnaPipe {
-    pub(crate) fn new(s3uri: S3Uri) -> ExRnaPipe {
-        ExRnaPipe { s3uri }
+    pub(crate) fn new(location: DataLocation) -> ExRnaPipe {
+        ExRnaPipe { location }
     }
 }
 
 impl LinePipe for ExRnaPipe {
     type Summary = ExRnaSummary;
-    fn s3uri(&self) -> &S3Uri { &self.s3uri }
+    fn location(&self) -> &DataLocation { &self.location }
     fn new_summary(&self) -> Self::Summary { ExRnaSummary::new() }
 }
 
-pub(crate) fn add_triples_ex_rna<W: GraphWriter>(writer: &mut W, runtime: &Runtime,
-                                                 gene_mapper: &GeneMapper,
-                                                 protein_mapper: &ProteinMapper,
-                                                 gene_tracker: &mut Tracker,
-                                                 protein_tracker: &mut Tracker)
-                                                 -> Result<(), Error> {
-    let summary = distill_ex_rna(runtime)?;
-    let molecularly_interacts_with = penyu::vocabs::obo::ns::RO.join_str("0002436");
-    let gene_type = vocabs::Concepts::Gene.concept_iri();
-    let protein_type = vocabs::Concepts::Protein.concept_iri();
-    for RbpGene { rbp, gene, read_count } in summary.rbp_genes.iter() {
-        let rbp_iri = distill::get_protein_uri(protein_mapper, rbp, protein_tracker);
-        writer.add_node(&rbp_iri, protein_type, rbp);
-        let gene_iri = distill::get_gene_iri(gene_mapper, gene, gene_tracker);
-        writer.add_node(&gene_iri, gene_type, gene);
-        let evidence_class = format!("read_count={read_count}");
-        writer.add_edge(&rbp_iri, &molecularly_interacts_with, &gene_iri, &evidence_class);
-    }
-    Ok(())
-}
\ No newline at end of file
+impl ExRnaDistiller {
+    pub(crate) fn new(config: Option<&Map<String, Value>>) -> Result<ExRnaDistiller, Error> {
+        let mut distiller =
+            ExRnaDistiller { aggregation: Aggregation::Sum, min_read_count: 0, top_k: None };
+        if let Some(config) = config {
+            if config.contains_key(keys::AGGREGATION) {
+                let aggregation = json::get_string(config, keys::AGGREGATION)?;
+                distiller.aggregation = match Aggregation::try_from(aggregation.as_str())? {
+                    Aggregation::NoisyOr => {
+                        return Err(Error::from(format!(
+                            "Aggregation '{}' only applies to probabilities, not read counts.",
+                            aggregation
+                        )));
+                    }
+                    aggregation => aggregation,
+                };
+            }
+            if config.contains_key(keys::MIN_READ_COUNT) {
+                distiller.min_read_count =
+                    json::get_number(config, keys::MIN_READ_COUNT)?.round() as u64;
+            }
+            if config.contains_key(keys::TOP_K) {
+                distiller.top_k = Some(json::get_number(config, keys::TOP_K)?.round() as usize);
+            }
+        }
+        Ok(distiller)
+    }
+    fn select<'a>(&self, summary: &'a ExRnaSummary) -> Vec<RbpGeneEdge<'a>> {
+        let mut rbp_to_genes: BTreeMap<&str, BTreeMap<&str, CountsRecords>> = BTreeMap::new();
+        for (RbpGene { rbp, gene, read_count }, lines) in &summary.rbp_genes {
+            let (read_counts, records) =
+                rbp_to_genes.entry(rbp).or_default().entry(gene).or_default();
+            read_counts.extend(std::iter::repeat_n(*read_count as f64, lines.len()));
+            records.extend(lines);
+        }
+        let mut edges: Vec<RbpGeneEdge> = Vec::new();
+        for (rbp, gene_counts) in rbp_to_genes {
+            let mut gene_counts: Vec<(&str, u64, Vec<RecordId>)> =
+                gene_counts.into_iter().map(|(gene, (read_counts, records))| {
+                    (gene, self.aggregation.aggregate(read_counts).round() as u64, records)
+                }).collect();
+            let total_count: u64 =
+                gene_counts.iter().map(|(_, read_count, _)| read_count).sum();
+            let mut sorted_counts: Vec<u64> =
+                gene_counts.iter().map(|(_, read_count, _)| *read_count).collect();
+            sorted_counts.sort();
+            gene_counts.retain(|(_, read_count, _)| *read_count >= self.min_read_count);
+            gene_counts.sort_by(|(gene1, count1, _), (gene2, count2, _)| {
+                count2.cmp(count1).then(gene1.cmp(gene2))
+            });
+            if let Some(top_k) = self.top_k {
+                gene_counts.truncate(top_k);
+            }
+            for (gene, read_count, records) in gene_counts {
+                let cpm = if total_count > 0 {
+                    (read_count as f64) * COUNTS_PER_MILLION / (total_count as f64)
+                } else {
+                    0.0
+                };
+                let n_at_most = sorted_counts.partition_point(|count| *count <= read_count);
+                let percentile = 100.0 * (n_at_most as f64) / (sorted_counts.len() as f64);
+                edges.push(RbpGeneEdge { rbp, gene, read_count, cpm, percentile, records });
+            }
+        }
+        edges
+    }
+    fn describe_policy(&self) -> String {
+        let top_k = match self.top_k {
+            Some(top_k) => format!(", top {} per RBP", top_k),
+            None => String::new(),
+        };
+        format!("aggregated by {}, minimum read count {}{}", self.aggregation,
+                self.min_read_count, top_k)
+    }
+}
+
+impl Distiller for ExRnaDistiller {
+    type Summary = ExRnaSummary;
+    fn name(&self) -> &str { NAME }
+    fn assertion_types(&self) -> Vec<&str> { vec![assertions::RBP_GENE] }
+    fn required_mappers(&self) -> Vec<MapperKind> { vec![MapperKind::Gene, MapperKind::Protein] }
+    fn new_summary(&self) -> ExRnaSummary { ExRnaSummary::new() }
+    fn summarize(&self, runtime: &Runtime, location: &DataLocation)
+                 -> Result<ExRnaSummary, Error> {
+        distill_ex_rna(runtime, location)
+    }
+    fn report_stats(&self, _source: &SourceDef, summary: ExRnaSummary,
+                    assertions: &BTreeSet<String>) -> usize {
+        println!("From the exRNA gene counts data:");
+        println!("Original records: {}", summary.n_original);
+        println!("Read counts: {}", self.describe_policy());
+        let edges = self.select(&summary);
+        let mut read_counts: Vec<u64> = edges.iter().map(|edge| edge.read_count).collect();
+        read_counts.sort();
+        if let (Some(min), Some(max)) = (read_counts.first(), read_counts.last()) {
+            let mean =
+                read_counts.iter().map(|count| *count as f64).sum::<f64>()
+                    / (read_counts.len() as f64);
+            println!("Retained read counts: min {}, quartile 1 {}, median {}, quartile 3 {}, \
+                     max {}, mean {}", min, quantile(&read_counts, 0.25),
+                     quantile(&read_counts, 0.5), quantile(&read_counts, 0.75), max,
+                     pretty_f64(mean));
+        }
+        distiller::report_assertions(assertions, assertions::RBP_GENE,
+                                     "RNA-binding protein - binds RNA - gene", edges.len())
+    }
+    fn count_records(&self, summary: &ExRnaSummary) -> RecordCounts {
+        RecordCounts {
+            original: summary.n_original,
+            filtered: None,
+            deduplicated: summary.rbp_genes.len(),
+        }
+    }
+    fn add_triples(&self, summary: ExRnaSummary, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<(), Error> {
+        let gene_mapper = context.mappers_chest.get_gene_mapper()?;
+        let protein_mapper = context.mappers_chest.get_protein_mapper()?;
+        let source = context.source;
+        let trackers = &mut context.trackers;
+        let gene_type = vocabs::Concepts::Gene.concept_iri();
+        let protein_type = vocabs::Concepts::Protein.concept_iri();
+        for RbpGeneEdge {
+            rbp, gene, read_count, cpm, percentile, records
+        } in self.select(&summary) {
+            let rbp_iri = distill::get_protein_uri(protein_mapper, rbp, &mut trackers.protein);
+            writer.add_node(&rbp_iri, protein_type, rbp);
+            let gene_iri = distill::get_gene_iri(gene_mapper, gene, &mut trackers.gene);
+            writer.add_node(&gene_iri, gene_type, gene);
+            let mut evidence = Evidence::new(source);
+            evidence.add_integer(evidence_names::READ_COUNT, read_count as i64);
+            evidence.add_text(evidence_names::AGGREGATION, &self.aggregation.to_string());
+            evidence.add_number(evidence_names::CPM, cpm);
+            evidence.add_number(evidence_names::PERCENTILE, percentile);
+            evidence.set_score(evidence_names::READ_COUNT, ScoreDirection::HigherIsBetter);
+            evidence.set_records(records);
+            writer.add_edge(&rbp_iri, Predicates::MolecularlyInteractsWith, &gene_iri, &evidence);
+        }
+        Ok(())
+    }
+}
+
+fn quantile(sorted: &[u64], fraction: f64) -> u64 {
+    let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+    sorted[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn summarize(lines: &[(&str, &str, u64)]) -> ExRnaSummary {
+        let mut summary = ExRnaSummary::new();
+        for (rbp, gene, read_count) in lines {
+            let line = json!({ "rbp": rbp, "gene_symbol": gene, "read_count": read_count });
+            summary = summary.next(line.to_string()).unwrap().summary;
+        }
+        summary
+    }
+
+    #[test]
+    fn sum_counts_every_record_even_with_equal_counts() {
+        let distiller = ExRnaDistiller::new(None).unwrap();
+        let summary = summarize(&[("A", "B", 5), ("A", "B", 5), ("A", "C", 5), ("A", "C", 6)]);
+        let edges = distiller.select(&summary);
+        let counts: Vec<(&str, u64, usize)> =
+            edges.iter().map(|edge| (edge.gene, edge.read_count, edge.records.len())).collect();
+        assert_eq!(counts, vec![("C", 11, 2), ("B", 10, 2)]);
+    }
+
+    #[test]
+    fn cpm_counts_every_record_even_with_equal_counts() {
+        let distiller = ExRnaDistiller::new(None).unwrap();
+        let summary = summarize(&[("A", "B", 5), ("A", "B", 5), ("A", "C", 10)]);
+        let edges = distiller.select(&summary);
+        let cpms: Vec<(&str, f64)> = edges.iter().map(|edge| (edge.gene, edge.cpm)).collect();
+        assert_eq!(cpms, vec![("B", 500_000.0), ("C", 500_000.0)]);
+    }
+
+    #[test]
+    fn records_are_numbered_per_object_counting_skipped_lines() {
+        let line = json!({ "rbp": "A", "gene_symbol": "B", "read_count": 1 }).to_string();
+        let mut summary = ExRnaSummary::new().start_object(1).skip_line();
+        summary = summary.next(line.clone()).unwrap().summary;
+        summary = summary.next(line.clone()).unwrap().summary;
+        summary = summary.start_object(2).next(line).unwrap().summary;
+        let records: Vec<String> =
+            summary.rbp_genes.values().flatten().map(|record| record.to_string()).collect();
+        assert_eq!(records, vec!["1:2", "1:3", "2:1"]);
+    }
+}
@@ -1,44 +1,67 @@
-use crate::data::sources;
+use crate::data::SourceDef;
+use crate::distill::aggregation::Aggregation;
+use crate::distill::distiller::{self, DistillContext, Distiller};
+use crate::distill::evidence::{Evidence, RecordId, ScoreDirection};
+use crate::distill::mappers::MapperKind;
+use crate::distill::report::RecordCounts;
 use crate::distill::util::{parse_mondo_id, pretty_f64, OrdF64};
 use crate::distill::write::GraphWriter;
 use crate::error::Error;
-use crate::mapper::hgnc::GeneMapper;
-use crate::mapper::track::Tracker;
+use crate::location::DataLocation;
 use crate::pipe::{LinePipe, NextSummary, Summary};
 use crate::runtime::Runtime;
-use crate::s3::S3Uri;
-use crate::vocabs::Concepts;
-use crate::{distill, json, s3};
-use std::collections::BTreeSet;
-use penyu::model::iri::Iri;
-use penyu::vocabs::obo::ns::RO;
-use crate::mapper::variants::VariantMapper;
-
-pub(crate) fn report_four_dn(runtime: &Runtime) -> Result<usize, Error> {
-    println!("From the 4DN gene bio data:");
-    let summary = distill_four_dn(runtime)?;
-    let n_assertions = summary.snp_genes_phenotypes.len();
-    println!("Original records: {}", summary.n_original);
-    println!("Assertions: lead SNP - target-gene-prediction - gene ({})", n_assertions);
-    println!("Assertions: lead SNP - associated with - Mondo ID ({})", n_assertions);
-    let n_assertions_total = 2 * n_assertions;
-    println!("Total assertions: {}", n_assertions_total);
-    Ok(n_assertions_total)
-}
-
-pub(crate) fn distill_four_dn(runtime: &Runtime) -> Result<FourDnSummary, Error> {
-    let s3uri = sources::FOURDN_GENE_BIO.to_s3uri();
-    let pipe = FourDnPipe::new(s3uri);
-    let summary = s3::process(runtime, &pipe)?;
+use crate::vocabs::{Concepts, Predicates};
+use crate::{distill, json, location};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub(crate) const NAME: &str = "4dn_gene_bio";
+
+pub(crate) mod assertions {
+    pub(crate) const GENE_DISEASE: &str = "gene_disease";
+    pub(crate) const SNP_GENE: &str = "snp_gene";
+    pub(crate) const SNP_DISEASE: &str = "snp_disease";
+    pub(crate) const ALL: [&str; 3] = [GENE_DISEASE, SNP_GENE, SNP_DISEASE];
+}
+
+const DEFAULT_MIN_POSTERIOR_PROBABILITY: f64 = 0.0;
+
+mod evidence_names {
+    pub(crate) const POSTERIOR_PROBABILITY: &str = "posterior_probability";
+    pub(crate) const AGGREGATION: &str = "aggregation";
+    pub(crate) const N_RECORDS: &str = "n_records";
+}
+
+mod keys {
+    pub(crate) const MIN_POSTERIOR_PROBABILITY: &str = "min_posterior_probability";
+    pub(crate) const AGGREGATION: &str = "aggregation";
+}
+
+pub(crate) struct FourDnDistiller {
+    min_posterior_probability: f64,
+    aggregation: Aggregation,
+}
+
+struct AggregatedEdge {
+    posterior_probability: f64,
+    n_records: usize,
+    records: Vec<RecordId>,
+}
+
+pub(crate) fn distill_four_dn(runtime: &Runtime, location: &DataLocation)
+    -> Result<FourDnSummary, Error> {
+    let pipe = FourDnPipe::new(location.clone());
+    let summary = location::process(runtime, &pipe)?;
     Ok(summary)
 }
 
 pub(crate) struct FourDnPipe {
-    s3uri: S3Uri,
+    location: DataLocation,
 }
 pub(crate) struct FourDnSummary {
     n_original: usize,
-    snp_genes_phenotypes: BTreeSet<SnpGenePhenotype>,
+    position: RecordId,
+    snp_genes_phenotypes: BTreeMap<SnpGenePhenotype, Vec<RecordId>>,
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq)]
@@ -54,7 +77,8 @@ impl FourDnSummary {
     pub(crate) fn new() -> FourDnSummary {
         FourDnSummary {
             n_original: 0,
-            snp_genes_phenotypes: BTreeSet::new(),
+            position: RecordId::new(),
+            snp_genes_phenotypes: BTreeMap::new(),
         }
     }
 }
@@ -74,69 +98,253 @@ impl Summary for FourDnSummary {
         };
         let FourDnSummary {
             mut n_original,
+            position,
             snp_genes_phenotypes: mut snp_genes_mondo_ids,
         } = self;
         n_original += 1;
-        snp_genes_mondo_ids.insert(snp_gene);
+        let position = position.next_line();
+        snp_genes_mondo_ids.entry(snp_gene).or_default().push(position);
         Ok(NextSummary {
             summary: FourDnSummary {
                 n_original,
+                position,
                 snp_genes_phenotypes: snp_genes_mondo_ids,
             },
         })
     }
+    fn start_object(self, index: usize) -> Self {
+        FourDnSummary { position: self.position.start_object(index), ..self }
+    }
+    fn skip_line(self) -> Self {
+        FourDnSummary { position: self.position.next_line(), ..self }
+    }
 }
 
 impl FourDnPipe {
-    pub(crate) fn new(s3uri: S3Uri) -> FourDnPipe {
-        FourDnPipe { s3uri }
+    pub(crate) fn new(location: DataLocation) -> FourDnPipe {
+        FourDnPipe { location }
     }
 }
 
 impl LinePipe for FourDnPipe {
     type Summary = FourDnSummary;
-    fn s3uri(&self) -> &S3Uri {
-        &self.s3uri
+    fn location(&self) -> &DataLocation {
+        &self.location
     }
     fn new_summary(&self) -> Self::Summary {
         FourDnSummary::new()
     }
 }
 
-pub(crate) fn add_triples_four_dn<W: GraphWriter>(
-    writer: &mut W,
-    runtime: &Runtime,
-    gene_mapper: &GeneMapper,
-    variant_mapper: &VariantMapper,
-    gene_tracker: &mut Tracker,
-    variant_tracker: &mut Tracker,
-    with_variants: bool
-) -> Result<(), Error> {
-    let summary = distill_four_dn(runtime)?;
-    let variant_type = Concepts::Variant.concept_iri();
-    let gene_type = Concepts::Gene.concept_iri();
-    let disease_type = Concepts::Disease.concept_iri();
-    let indirectly_positively_regulates_activity_of = RO.join_str("0011013");
-    let contributes_to_frequency_of_condition = RO.join_str("0003306");
-    let associated_with = Iri::from("associated_with");
-    for SnpGenePhenotype {
-        snp, gene, phenotype, mondo_id, posterior_probability
-    } in summary.snp_genes_phenotypes {
-        let gene_iri = distill::get_gene_iri(gene_mapper, &gene, gene_tracker);
-        writer.add_node(&gene_iri, gene_type, &gene);
-        let mondo_iri = penyu::vocabs::obo::Ontology::MONDO.create_iri(mondo_id);
-        writer.add_node(&mondo_iri, disease_type, &phenotype);
-        let evidence_class =
-            format!("posterior_probability={}", pretty_f64(posterior_probability.value));
-        writer.add_edge(&gene_iri, &associated_with, &mondo_iri, &evidence_class);
-        if with_variants {
-            let snp_iri = distill::get_variant_iri(variant_mapper, &snp, variant_tracker);
-            writer.add_node(&snp_iri, variant_type, &snp);
-            writer.add_edge(&snp_iri, &indirectly_positively_regulates_activity_of, &gene_iri,
-                            &evidence_class);
-            writer.add_edge(&snp_iri, &contributes_to_frequency_of_condition, &mondo_iri,
-                            &evidence_class);
+impl FourDnDistiller {
+    pub(crate) fn new(config: Option<&Map<String, Value>>) -> Result<FourDnDistiller, Error> {
+        let mut distiller = FourDnDistiller {
+            min_posterior_probability: DEFAULT_MIN_POSTERIOR_PROBABILITY,
+            aggregation: Aggregation::Max,
+        };
+        if let Some(config) = config {
+            if config.contains_key(keys::MIN_POSTERIOR_PROBABILITY) {
+                distiller.min_posterior_probability =
+                    json::get_number(config, keys::MIN_POSTERIOR_PROBABILITY)?;
+            }
+            if config.contains_key(keys::AGGREGATION) {
+                let aggregation = json::get_string(config, keys::AGGREGATION)?;
+                distiller.aggregation = match Aggregation::try_from(aggregation.as_str())? {
+                    Aggregation::Sum => {
+                        return Err(Error::from(format!(
+                            "Aggregation '{}' does not yield a probability, so it does not \
+                            apply to posterior probabilities.", aggregation
+                        )));
+                    }
+                    aggregation => aggregation,
+                };
+            }
         }
+        Ok(distiller)
+    }
+    fn aggregate<'a, K, F>(&self, summary: &'a FourDnSummary, key: F)
+        -> BTreeMap<K, AggregatedEdge>
+    where
+        K: Ord,
+        F: Fn(&'a SnpGenePhenotype) -> K,
+    {
+        let mut groups: BTreeMap<K, (Vec<f64>, Vec<RecordId>)> = BTreeMap::new();
+        for (record, lines) in &summary.snp_genes_phenotypes {
+            let posterior_probability = record.posterior_probability.value;
+            if posterior_probability >= self.min_posterior_probability {
+                let (posterior_probabilities, records) = groups.entry(key(record)).or_default();
+                posterior_probabilities.push(posterior_probability);
+                records.extend(lines);
+            }
+        }
+        groups.into_iter().map(|(key, (posterior_probabilities, records))| {
+            let edge = AggregatedEdge {
+                posterior_probability:
+                    self.aggregation.aggregate(posterior_probabilities.iter().copied()),
+                n_records: records.len(),
+                records,
+            };
+            (key, edge)
+        }).collect()
+    }
+    fn evidence(&self, source: &str, edge: AggregatedEdge) -> Evidence {
+        let mut evidence = Evidence::new(source);
+        evidence.add_number(evidence_names::POSTERIOR_PROBABILITY, edge.posterior_probability);
+        evidence.add_text(evidence_names::AGGREGATION, &self.aggregation.to_string());
+        evidence.add_integer(evidence_names::N_RECORDS, edge.n_records as i64);
+        evidence.set_score(evidence_names::POSTERIOR_PROBABILITY, ScoreDirection::HigherIsBetter);
+        evidence.set_records(edge.records);
+        evidence
+    }
+}
+
+impl Distiller for FourDnDistiller {
+    type Summary = FourDnSummary;
+    fn name(&self) -> &str { NAME }
+    fn assertion_types(&self) -> Vec<&str> { assertions::ALL.to_vec() }
+    fn required_mappers(&self) -> Vec<MapperKind> { vec![MapperKind::Gene, MapperKind::Variant] }
+    fn new_summary(&self) -> FourDnSummary { FourDnSummary::new() }
+    fn summarize(&self, runtime: &Runtime, location: &DataLocation)
+                 -> Result<FourDnSummary, Error> {
+        distill_four_dn(runtime, location)
+    }
+    fn report_stats(&self, _source: &SourceDef, summary: FourDnSummary,
+                    assertions: &BTreeSet<String>) -> usize {
+        println!("From the 4DN gene bio data:");
+        println!("Original records: {}", summary.n_original);
+        println!("Minimum posterior probability: {}, aggregated by {}",
+                 pretty_f64(self.min_posterior_probability), self.aggregation);
+        let n_gene_disease = self.aggregate(&summary, gene_disease_key).len();
+        let n_snp_gene = self.aggregate(&summary, snp_gene_key).len();
+        let n_snp_disease = self.aggregate(&summary, snp_disease_key).len();
+        let n_gene_disease = distiller::report_assertions(
+            assertions, assertions::GENE_DISEASE, "gene - associated with - Mondo ID",
+            n_gene_disease
+        );
+        let n_snp_gene = distiller::report_assertions(
+            assertions, assertions::SNP_GENE, "lead SNP - target-gene-prediction - gene",
+            n_snp_gene
+        );
+        let n_snp_disease = distiller::report_assertions(
+            assertions, assertions::SNP_DISEASE, "lead SNP - associated with - Mondo ID",
+            n_snp_disease
+        );
+        let n_assertions_total = n_gene_disease + n_snp_gene + n_snp_disease;
+        println!("Total assertions: {}", n_assertions_total);
+        n_assertions_total
+    }
+    fn count_records(&self, summary: &FourDnSummary) -> RecordCounts {
+        RecordCounts {
+            original: summary.n_original,
+            filtered: None,
+            deduplicated: summary.snp_genes_phenotypes.len(),
+        }
+    }
+    fn add_triples(&self, summary: FourDnSummary, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<(), Error> {
+        let gene_mapper = context.mappers_chest.get_gene_mapper()?;
+        let variant_mapper = context.mappers_chest.get_variant_mapper()?;
+        let with_gene_disease = context.includes(assertions::GENE_DISEASE);
+        let with_snp_gene = context.includes(assertions::SNP_GENE);
+        let with_snp_disease = context.includes(assertions::SNP_DISEASE);
+        let source = context.source;
+        let trackers = &mut context.trackers;
+        let variant_type = Concepts::Variant.concept_iri();
+        let gene_type = Concepts::Gene.concept_iri();
+        let disease_type = Concepts::Disease.concept_iri();
+        let phenotypes: BTreeMap<u32, &str> = summary.snp_genes_phenotypes.keys()
+            .map(|record| (record.mondo_id, record.phenotype.as_str())).collect();
+        if with_gene_disease {
+            for ((gene, mondo_id), edge) in self.aggregate(&summary, gene_disease_key) {
+                let gene_iri = distill::get_gene_iri(gene_mapper, gene, &mut trackers.gene);
+                writer.add_node(&gene_iri, gene_type, gene);
+                let mondo_iri = penyu::vocabs::obo::Ontology::MONDO.create_iri(mondo_id);
+                writer.add_node(&mondo_iri, disease_type, phenotypes[&mondo_id]);
+                writer.add_edge(&gene_iri, Predicates::AssociatedWith, &mondo_iri,
+                                &self.evidence(source, edge));
+            }
+        }
+        if with_snp_gene {
+            for ((snp, gene), edge) in self.aggregate(&summary, snp_gene_key) {
+                let snp_iri =
+                    distill::get_variant_iri(variant_mapper, snp, &mut trackers.variant);
+                writer.add_node(&snp_iri, variant_type, snp);
+                let gene_iri = distill::get_gene_iri(gene_mapper, gene, &mut trackers.gene);
+                writer.add_node(&gene_iri, gene_type, gene);
+                writer.add_edge(&snp_iri, Predicates::IndirectlyPositivelyRegulatesActivityOf,
+                                &gene_iri, &self.evidence(source, edge));
+            }
+        }
+        if with_snp_disease {
+            for ((snp, mondo_id), edge) in self.aggregate(&summary, snp_disease_key) {
+                let snp_iri =
+                    distill::get_variant_iri(variant_mapper, snp, &mut trackers.variant);
+                writer.add_node(&snp_iri, variant_type, snp);
+                let mondo_iri = penyu::vocabs::obo::Ontology::MONDO.create_iri(mondo_id);
+                writer.add_node(&mondo_iri, disease_type, phenotypes[&mondo_id]);
+                writer.add_edge(&snp_iri, Predicates::ContributesToFrequencyOfCondition,
+                                &mondo_iri, &self.evidence(source, edge));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn gene_disease_key(record: &SnpGenePhenotype) -> (&str, u32) {
+    (record.gene.as_str(), record.mondo_id)
+}
+
+fn snp_gene_key(record: &SnpGenePhenotype) -> (&str, &str) {
+    (record.snp.as_str(), record.gene.as_str())
+}
+
+fn snp_disease_key(record: &SnpGenePhenotype) -> (&str, u32) {
+    (record.snp.as_str(), record.mondo_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn summarize(lines: &[(&str, &str, f64)]) -> FourDnSummary {
+        let mut summary = FourDnSummary::new();
+        for (snp, gene, posterior_probability) in lines {
+            let line = json!({
+                "leadSNP": snp, "gene": gene, "phenotype": "T2D", "mondo_id": "MONDO:0005148",
+                "posteriorProbability": posterior_probability
+            });
+            summary = summary.next(line.to_string()).unwrap().summary;
+        }
+        summary
+    }
+
+    fn distiller(aggregation: &str) -> Result<FourDnDistiller, Error> {
+        let config = json!({ "aggregation": aggregation });
+        FourDnDistiller::new(config.as_object())
+    }
+
+    #[test]
+    fn max_takes_highest_posterior_probability() {
+        let summary = summarize(&[("rs1", "A", 0.2), ("rs2", "A", 0.7), ("rs3", "A", 0.5)]);
+        let edges = distiller("max").unwrap().aggregate(&summary, gene_disease_key);
+        let edge = &edges[&("A", 5148)];
+        assert_eq!(edge.posterior_probability, 0.7);
+        assert_eq!(edge.n_records, 3);
+    }
+
+    #[test]
+    fn noisy_or_counts_duplicate_rows_once() {
+        let summary = summarize(&[("rs1", "A", 0.5), ("rs1", "A", 0.5), ("rs2", "A", 0.5)]);
+        let edges = distiller("noisy_or").unwrap().aggregate(&summary, gene_disease_key);
+        let edge = &edges[&("A", 5148)];
+        assert_eq!(edge.posterior_probability, 0.75);
+        assert_eq!(edge.n_records, 3);
+        assert_eq!(edge.records.len(), 3);
+    }
+
+    #[test]
+    fn sum_is_rejected() {
+        assert!(distiller("sum").is_err());
     }
-    Ok(())
 }
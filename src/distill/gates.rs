@@ -0,0 +1,87 @@
+use crate::distill::tracker_names;
+use crate::error::Error;
+use crate::mapper::track::Tracker;
+use std::collections::BTreeMap;
+
+/// Minimum mapping rates and maximum unmapped counts per tracker, checked before writing output.
+pub(crate) struct QualityGates {
+    min_mapped: BTreeMap<String, f64>,
+    max_unmapped: BTreeMap<String, usize>,
+}
+
+impl QualityGates {
+    pub(crate) fn new() -> QualityGates {
+        QualityGates { min_mapped: BTreeMap::new(), max_unmapped: BTreeMap::new() }
+    }
+    pub(crate) fn n_gates(&self) -> usize {
+        self.min_mapped.len() + self.max_unmapped.len()
+    }
+    /// Parses `<tracker>=<rate>,...`, with rates between 0 and 1.
+    pub(crate) fn parse_min_mapped(&mut self, string: &str) -> Result<(), Error> {
+        for (tracker, value) in parse_entries(string)? {
+            let rate = value.parse::<f64>().map_err(|error| {
+                Error::wrap(format!("Invalid minimum mapping rate for '{}'", tracker), error)
+            })?;
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(Error::from(format!(
+                    "Minimum mapping rate for '{}' needs to be between 0 and 1, but is {}.",
+                    tracker, rate
+                )));
+            }
+            self.min_mapped.insert(tracker, rate);
+        }
+        Ok(())
+    }
+    /// Parses `<tracker>=<count>,...`.
+    pub(crate) fn parse_max_unmapped(&mut self, string: &str) -> Result<(), Error> {
+        for (tracker, value) in parse_entries(string)? {
+            let count = value.parse::<usize>().map_err(|error| {
+                Error::wrap(format!("Invalid maximum unmapped count for '{}'", tracker), error)
+            })?;
+            self.max_unmapped.insert(tracker, count);
+        }
+        Ok(())
+    }
+    /// Describes each failed gate. Trackers that saw nothing to map pass the rate gates.
+    pub(crate) fn check(&self, trackers: &[&Tracker]) -> Vec<String> {
+        let mut failures: Vec<String> = Vec::new();
+        for tracker in trackers {
+            let n_total = tracker.n_hit() + tracker.n_miss();
+            if let Some(min_rate) = self.min_mapped.get(tracker.name())
+                && n_total > 0 {
+                let rate = tracker.n_hit() as f64 / n_total as f64;
+                if rate < *min_rate {
+                    failures.push(format!(
+                        "{}: mapped {:.1}% of {}, below minimum of {:.1}% by {:.1} points",
+                        tracker.name(), 100.0 * rate, n_total, 100.0 * min_rate,
+                        100.0 * (min_rate - rate)
+                    ));
+                }
+            }
+            if let Some(max_unmapped) = self.max_unmapped.get(tracker.name())
+                && tracker.n_missing() > *max_unmapped {
+                failures.push(format!(
+                    "{}: {} unmapped, above maximum of {} by {}", tracker.name(),
+                    tracker.n_missing(), max_unmapped, tracker.n_missing() - max_unmapped
+                ));
+            }
+        }
+        failures
+    }
+}
+
+fn parse_entries(string: &str) -> Result<Vec<(String, String)>, Error> {
+    string.split(',').map(|entry| {
+        let (tracker, value) = entry.split_once('=').ok_or_else(|| {
+            Error::from(format!("Expected '<tracker>=<value>', but got '{}'.", entry))
+        })?;
+        let tracker = tracker.trim();
+        if !tracker_names::ALL.contains(&tracker) {
+            return Err(Error::from(format!(
+                "Unknown tracker '{}'. Known trackers are '{}'.", tracker,
+                tracker_names::ALL.join("', '")
+            )));
+        }
+        Ok((tracker.to_string(), value.trim().to_string()))
+    }).collect()
+}
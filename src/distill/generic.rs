@@ -0,0 +1,612 @@
+use crate::data::SourceDef;
+use crate::distill::distiller::{self, DistillContext, Distiller};
+use crate::distill::evidence::{Evidence, EvidenceValue, RecordId, ScoreDirection};
+use crate::distill::mappers::{MapperKind, MappersChest};
+use crate::distill::report::RecordCounts;
+use crate::distill::util::parse_mondo_id;
+use crate::distill::write::GraphWriter;
+use crate::distill::Trackers;
+use crate::error::Error;
+use crate::expr::{as_string, FieldPath};
+use crate::filter::Filter;
+use crate::location::DataLocation;
+use crate::pipe::{LinePipe, NextSummary, Summary};
+use crate::runtime::Runtime;
+use crate::vocabs::{Concepts, PredicateDef, Predicates};
+use crate::{distill, json, location};
+use penyu::model::iri::Iri;
+use serde_json::{Map, Value};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+pub(crate) const NAME: &str = "generic";
+
+mod keys {
+    pub(crate) const SUBJECT: &str = "subject";
+    pub(crate) const OBJECT: &str = "object";
+    pub(crate) const PREDICATE: &str = "predicate";
+    pub(crate) const FILTER: &str = "filter";
+    pub(crate) const DEDUP: &str = "dedup";
+    pub(crate) const EVIDENCE: &str = "evidence";
+    pub(crate) const FIELD: &str = "field";
+    pub(crate) const LABEL: &str = "label";
+    pub(crate) const CONCEPT: &str = "concept";
+    pub(crate) const ASSERTION: &str = "assertion";
+    pub(crate) const SCORE: &str = "score";
+    pub(crate) const SCORE_DIRECTION: &str = "score_direction";
+}
+
+mod predicate_keys {
+    pub(crate) const IRI: &str = "iri";
+    pub(crate) const CURIE: &str = "curie";
+    pub(crate) const LABEL: &str = "label";
+    pub(crate) const BIOLINK: &str = "biolink";
+    pub(crate) const INVERSE: &str = "inverse";
+}
+
+const DEFAULT_ASSERTION: &str = "edge";
+
+mod concepts {
+    pub(crate) const TISSUE: &str = "tissue";
+    pub(crate) const GENE: &str = "gene";
+    pub(crate) const DISEASE: &str = "disease";
+    pub(crate) const VARIANT: &str = "variant";
+    pub(crate) const PROTEIN: &str = "protein";
+    pub(crate) const ALL: [&str; 5] = [TISSUE, GENE, DISEASE, VARIANT, PROTEIN];
+}
+
+pub(crate) struct GenericDistiller {
+    spec: Rc<GenericSpec>,
+}
+
+struct GenericSpec {
+    subject: EntitySpec,
+    object: EntitySpec,
+    predicate: Predicates,
+    filter: Option<Filter>,
+    dedup: Vec<FieldPath>,
+    evidence: Vec<(String, FieldPath)>,
+    score: Option<(String, ScoreDirection)>,
+    assertion: String,
+}
+
+struct EntitySpec {
+    field: String,
+    path: FieldPath,
+    label: Option<FieldPath>,
+    concept: Concepts,
+    concept_name: String,
+}
+
+impl GenericDistiller {
+    pub(crate) fn from_json(map: &Map<String, Value>) -> Result<GenericDistiller, Error> {
+        let spec = GenericSpec::from_json(map)?;
+        Ok(GenericDistiller { spec: Rc::new(spec) })
+    }
+}
+
+impl GenericSpec {
+    fn from_json(map: &Map<String, Value>) -> Result<GenericSpec, Error> {
+        let subject = EntitySpec::from_json(get_object(map, keys::SUBJECT)?)
+            .map_err(|error| Error::wrap(format!("Invalid '{}'", keys::SUBJECT), error))?;
+        let object = EntitySpec::from_json(get_object(map, keys::OBJECT)?)
+            .map_err(|error| Error::wrap(format!("Invalid '{}'", keys::OBJECT), error))?;
+        let predicate = match map.get(keys::PREDICATE) {
+            Some(Value::Object(predicate)) => define_predicate(predicate, &subject, &object)
+                .map_err(|error| {
+                    Error::wrap(format!("Invalid '{}'", keys::PREDICATE), error)
+                })?,
+            _ => parse_predicate(&json::get_string(map, keys::PREDICATE)?)?,
+        };
+        let filter = match map.get(keys::FILTER) {
+            Some(_) => Some(Filter::parse(&json::get_string(map, keys::FILTER)?)?),
+            None => None,
+        };
+        let evidence = json::get_strings(map, keys::EVIDENCE)?.into_iter().map(|field| {
+            let path = FieldPath::parse(&field)?;
+            Ok((field, path))
+        }).collect::<Result<Vec<(String, FieldPath)>, Error>>()?;
+        let dedup = match map.get(keys::DEDUP) {
+            Some(_) => {
+                json::get_strings(map, keys::DEDUP)?.iter().map(|field| FieldPath::parse(field))
+                    .collect::<Result<Vec<FieldPath>, Error>>()?
+            }
+            None => {
+                let mut dedup = vec![subject.path.clone(), object.path.clone()];
+                dedup.extend(evidence.iter().map(|(_, path)| path.clone()));
+                dedup
+            }
+        };
+        let assertion = match map.get(keys::ASSERTION) {
+            Some(_) => json::get_string(map, keys::ASSERTION)?,
+            None => DEFAULT_ASSERTION.to_string(),
+        };
+        let score = match map.get(keys::SCORE) {
+            Some(_) => {
+                let score = json::get_string(map, keys::SCORE)?;
+                if !evidence.iter().any(|(field, _)| field == &score) {
+                    return Err(Error::from(format!(
+                        "Score '{}' needs to be one of the '{}' fields.", score, keys::EVIDENCE
+                    )));
+                }
+                let direction = ScoreDirection::try_from(
+                    json::get_string(map, keys::SCORE_DIRECTION)?.as_str()
+                )?;
+                Some((score, direction))
+            }
+            None => None,
+        };
+        Ok(GenericSpec {
+            subject, object, predicate, filter, dedup, evidence, score, assertion
+        })
+    }
+}
+
+impl EntitySpec {
+    fn from_json(map: &Map<String, Value>) -> Result<EntitySpec, Error> {
+        let field = json::get_string(map, keys::FIELD)?;
+        let path = FieldPath::parse(&field)?;
+        let label = match map.get(keys::LABEL) {
+            Some(_) => Some(FieldPath::parse(&json::get_string(map, keys::LABEL)?)?),
+            None => None,
+        };
+        let concept_name = json::get_string(map, keys::CONCEPT)?;
+        let concept = parse_concept(&concept_name)?;
+        Ok(EntitySpec { field, path, label, concept, concept_name })
+    }
+    fn get_id_label(&self, record: &Map<String, Value>) -> Result<(String, String), Error> {
+        let id = match self.path.resolve(record) {
+            Some(Value::Null) | None => {
+                return Err(Error::from(format!("Missing field '{}'", self.field)));
+            }
+            Some(value) => as_string(value),
+        };
+        let label = match self.label.as_ref().and_then(|label| label.resolve(record)) {
+            Some(Value::Null) | None => id.clone(),
+            Some(value) => as_string(value),
+        };
+        Ok((id, label))
+    }
+    fn mapper_kind(&self) -> Option<MapperKind> {
+        match self.concept {
+            Concepts::Tissue => Some(MapperKind::Tissue),
+            Concepts::Gene => Some(MapperKind::Gene),
+            Concepts::Protein => Some(MapperKind::Protein),
+            Concepts::Variant => Some(MapperKind::Variant),
+            Concepts::Disease => None,
+        }
+    }
+    fn map_iri(&self, id: &str, mappers_chest: &MappersChest, trackers: &mut Trackers)
+               -> Result<Iri, Error> {
+        match self.concept {
+            Concepts::Tissue => {
+                let tissue_mapper = mappers_chest.get_tissue_mapper()?;
+                Ok(distill::get_tissue_iri(tissue_mapper, id, &mut trackers.tissue))
+            }
+            Concepts::Gene => {
+                let gene_mapper = mappers_chest.get_gene_mapper()?;
+                Ok(distill::get_gene_iri(gene_mapper, id, &mut trackers.gene))
+            }
+            Concepts::Protein => {
+                let protein_mapper = mappers_chest.get_protein_mapper()?;
+                Ok(distill::get_protein_uri(protein_mapper, id, &mut trackers.protein))
+            }
+            Concepts::Variant => {
+                let variant_mapper = mappers_chest.get_variant_mapper()?;
+                Ok(distill::get_variant_iri(variant_mapper, id, &mut trackers.variant))
+            }
+            Concepts::Disease => {
+                Ok(penyu::vocabs::obo::Ontology::MONDO.create_iri(parse_mondo_id(id)?))
+            }
+        }
+    }
+}
+
+fn get_object<'a>(map: &'a Map<String, Value>, key: &str)
+                  -> Result<&'a Map<String, Value>, Error> {
+    match map.get(key) {
+        Some(Value::Object(object)) => Ok(object),
+        Some(value) => {
+            Err(Error::from(format!("Expected object field '{}', but got {}", key, value)))
+        }
+        None => Err(Error::from(format!("Missing field '{}'", key))),
+    }
+}
+
+fn parse_concept(string: &str) -> Result<Concepts, Error> {
+    match string {
+        concepts::TISSUE => Ok(Concepts::Tissue),
+        concepts::GENE => Ok(Concepts::Gene),
+        concepts::DISEASE => Ok(Concepts::Disease),
+        concepts::VARIANT => Ok(Concepts::Variant),
+        concepts::PROTEIN => Ok(Concepts::Protein),
+        _ => Err(Error::from(format!(
+            "Unknown concept '{}'. Known concepts are '{}'.", string,
+            concepts::ALL.join("', '")
+        ))),
+    }
+}
+
+fn parse_predicate(string: &str) -> Result<Predicates, Error> {
+    Predicates::find(string).ok_or_else(|| {
+        let known: Vec<String> = Predicates::ALL.iter().map(|predicate| {
+            let def = predicate.def();
+            format!("{} ({})", def.name, def.curie)
+        }).collect();
+        Error::from(format!(
+            "Unknown predicate '{}'. Known predicates are {}. Other predicates can be defined \
+            with an object with keys '{}', '{}', '{}', '{}' and '{}'.", string, known.join(", "),
+            predicate_keys::IRI, predicate_keys::CURIE, predicate_keys::LABEL,
+            predicate_keys::BIOLINK, predicate_keys::INVERSE
+        ))
+    })
+}
+
+/// Registers a predicate that is not built in. Its domain and range are the subject and object
+/// concepts.
+fn define_predicate(map: &Map<String, Value>, subject: &EntitySpec, object: &EntitySpec)
+    -> Result<Predicates, Error> {
+    let iri = json::get_string(map, predicate_keys::IRI)?;
+    let curie = json::get_string(map, predicate_keys::CURIE)?;
+    for string in [&iri, &curie] {
+        if let Some(predicate) = Predicates::find(string) {
+            return Err(Error::from(format!(
+                "Predicate '{}' is already known, use '{}' instead.", string,
+                predicate.def().name
+            )));
+        }
+    }
+    let def = PredicateDef {
+        name: leak(curie.clone()),
+        curie: leak(curie),
+        label: leak(json::get_string(map, predicate_keys::LABEL)?),
+        biolink: leak(json::get_string(map, predicate_keys::BIOLINK)?),
+        inverse: leak(json::get_string(map, predicate_keys::INVERSE)?),
+        domain: Box::leak(Box::new([subject.concept])),
+        range: Box::leak(Box::new([object.concept])),
+    };
+    Ok(Predicates::register(Iri::from(iri), def))
+}
+
+fn leak(string: String) -> &'static str {
+    Box::leak(string.into_boxed_str())
+}
+
+fn distill_generic(runtime: &Runtime, location: &DataLocation, spec: &Rc<GenericSpec>)
+    -> Result<GenericSummary, Error> {
+    let pipe = GenericPipe::new(location.clone(), spec.clone());
+    let summary = location::process(runtime, &pipe)?;
+    Ok(summary)
+}
+
+struct GenericPipe {
+    location: DataLocation,
+    spec: Rc<GenericSpec>,
+}
+
+pub(crate) struct GenericSummary {
+    spec: Rc<GenericSpec>,
+    n_original: usize,
+    n_filtered: usize,
+    position: RecordId,
+    assertions: BTreeMap<Vec<String>, Assertion>,
+}
+
+struct Assertion {
+    subject: String,
+    subject_label: String,
+    object: String,
+    object_label: String,
+    evidence: Vec<(String, EvidenceValue)>,
+    records: Vec<RecordId>,
+}
+
+impl GenericSummary {
+    fn new(spec: Rc<GenericSpec>) -> GenericSummary {
+        GenericSummary {
+            spec, n_original: 0, n_filtered: 0, position: RecordId::new(),
+            assertions: BTreeMap::new(),
+        }
+    }
+}
+
+impl Summary for GenericSummary {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let json_obj = json::as_json_obj(&line)?;
+        let mut summary = self;
+        summary.n_original += 1;
+        summary.position = summary.position.next_line();
+        let spec = &summary.spec;
+        if spec.filter.as_ref().is_none_or(|filter| filter.matches(&json_obj)) {
+            summary.n_filtered += 1;
+            let key: Vec<String> = spec.dedup.iter().map(|path| {
+                path.resolve(&json_obj).map(as_string).unwrap_or_default()
+            }).collect();
+            let record = summary.position;
+            match summary.assertions.entry(key) {
+                Entry::Vacant(entry) => {
+                    let (subject, subject_label) = spec.subject.get_id_label(&json_obj)?;
+                    let (object, object_label) = spec.object.get_id_label(&json_obj)?;
+                    let evidence = get_evidence_values(&spec.evidence, &json_obj);
+                    let records = vec![record];
+                    let assertion = Assertion {
+                        subject, subject_label, object, object_label, evidence, records
+                    };
+                    entry.insert(assertion);
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().records.push(record),
+            }
+        }
+        Ok(NextSummary { summary })
+    }
+    fn start_object(self, index: usize) -> Self {
+        GenericSummary { position: self.position.start_object(index), ..self }
+    }
+    fn skip_line(self) -> Self {
+        GenericSummary { position: self.position.next_line(), ..self }
+    }
+}
+
+fn get_evidence_values(evidence: &[(String, FieldPath)], record: &Map<String, Value>)
+    -> Vec<(String, EvidenceValue)> {
+    evidence.iter().map(|(field, path)| {
+        let value = match path.resolve(record) {
+            Some(Value::Number(number)) => match (number.as_i64(), number.as_f64()) {
+                (Some(integer), _) => EvidenceValue::Integer(integer),
+                (None, Some(number)) => EvidenceValue::Number(number),
+                (None, None) => EvidenceValue::Text(number.to_string()),
+            },
+            Some(value) => EvidenceValue::Text(as_string(value)),
+            None => EvidenceValue::Text(String::new()),
+        };
+        (field.clone(), value)
+    }).collect()
+}
+
+impl GenericPipe {
+    fn new(location: DataLocation, spec: Rc<GenericSpec>) -> GenericPipe {
+        GenericPipe { location, spec }
+    }
+}
+
+impl LinePipe for GenericPipe {
+    type Summary = GenericSummary;
+    fn location(&self) -> &DataLocation { &self.location }
+    fn new_summary(&self) -> Self::Summary { GenericSummary::new(self.spec.clone()) }
+}
+
+impl Distiller for GenericDistiller {
+    type Summary = GenericSummary;
+    fn name(&self) -> &str { NAME }
+    fn assertion_types(&self) -> Vec<&str> { vec![self.spec.assertion.as_str()] }
+    fn required_mappers(&self) -> Vec<MapperKind> {
+        [&self.spec.subject, &self.spec.object].iter()
+            .filter_map(|entity| entity.mapper_kind()).collect()
+    }
+    fn new_summary(&self) -> GenericSummary { GenericSummary::new(self.spec.clone()) }
+    fn summarize(&self, runtime: &Runtime, location: &DataLocation)
+                 -> Result<GenericSummary, Error> {
+        distill_generic(runtime, location, &self.spec)
+    }
+    fn report_stats(&self, source: &SourceDef, summary: GenericSummary,
+                    assertions: &BTreeSet<String>) -> usize {
+        let spec = &self.spec;
+        println!("From the {} data:", source.name);
+        println!("Original records: {}", summary.n_original);
+        if spec.filter.is_some() {
+            println!("Filtered records: {}", summary.n_filtered);
+        }
+        let description = format!("{} - {} - {}", spec.subject.concept_name,
+                                  spec.predicate.def().label, spec.object.concept_name);
+        distiller::report_assertions(assertions, &spec.assertion, &description,
+                                     summary.assertions.len())
+    }
+    fn count_records(&self, summary: &GenericSummary) -> RecordCounts {
+        RecordCounts {
+            original: summary.n_original,
+            filtered: self.spec.filter.as_ref().map(|_| summary.n_filtered),
+            deduplicated: summary.assertions.len(),
+        }
+    }
+    fn add_triples(&self, summary: GenericSummary, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<(), Error> {
+        let spec = &self.spec;
+        let mappers_chest = context.mappers_chest;
+        let source = context.source;
+        let trackers = &mut context.trackers;
+        let subject_type = spec.subject.concept.concept_iri();
+        let object_type = spec.object.concept.concept_iri();
+        for assertion in summary.assertions.values() {
+            let subject_iri =
+                spec.subject.map_iri(&assertion.subject, mappers_chest, trackers)?;
+            writer.add_node(&subject_iri, subject_type, &assertion.subject_label);
+            let object_iri = spec.object.map_iri(&assertion.object, mappers_chest, trackers)?;
+            writer.add_node(&object_iri, object_type, &assertion.object_label);
+            let mut evidence = Evidence::new(source);
+            for (field, value) in &assertion.evidence {
+                evidence.add(field, value.clone());
+            }
+            if let Some((score, direction)) = &spec.score {
+                evidence.set_score(score, *direction);
+            }
+            evidence.set_records(assertion.records.clone());
+            writer.add_edge(&subject_iri, spec.predicate, &object_iri, &evidence);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distill::write::labels::LabelConflicts;
+    use crate::location::ObjectInfo;
+    use serde_json::json;
+
+    /// Keeps the edges, as subject, predicate, object and evidence.
+    struct EdgeCollector {
+        edges: Vec<(Iri, Predicates, Iri, Evidence)>,
+    }
+
+    impl GraphWriter for EdgeCollector {
+        fn add_node(&mut self, _node: &Iri, _class: &Iri, _label: &str) {}
+        fn add_ontology_label(&mut self, _node: &Iri, _label: &str) {}
+        fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                    evidence: &Evidence) {
+            self.edges.push((subject.clone(), predicate, object.clone(), evidence.clone()));
+        }
+        fn add_source(&mut self, _source: &SourceDef, _objects: &[ObjectInfo]) {}
+        fn label_conflicts(&self) -> LabelConflicts { LabelConflicts::new() }
+        fn serialize(&mut self) -> Result<(), Error> { Ok(()) }
+    }
+
+    /// Disease to disease, so that no mappers need to be loaded.
+    fn disease_spec(extra: Value) -> GenericDistiller {
+        let mut spec = json!({
+            "subject": { "field": "disease", "concept": "disease" },
+            "object": { "field": "related.id", "concept": "disease" },
+            "predicate": "associated_with",
+            "evidence": ["score"],
+            "score": "score",
+            "score_direction": "higher",
+        });
+        let map = spec.as_object_mut().unwrap();
+        map.extend(extra.as_object().unwrap().clone());
+        GenericDistiller::from_json(map).unwrap()
+    }
+
+    fn summarize(distiller: &GenericDistiller, records: &[Value]) -> GenericSummary {
+        let mut summary = distiller.new_summary();
+        for record in records {
+            summary = summary.next(record.to_string()).unwrap().summary;
+        }
+        summary
+    }
+
+    fn distill(distiller: &GenericDistiller, summary: GenericSummary)
+               -> Vec<(Iri, Predicates, Iri, Evidence)> {
+        let mappers_chest = MappersChest::new().unwrap();
+        let mut trackers = Trackers::new();
+        let assertions = BTreeSet::new();
+        let mut context = DistillContext {
+            mappers_chest: &mappers_chest, trackers: &mut trackers, assertions: &assertions,
+            source: "test",
+        };
+        let mut writer = EdgeCollector { edges: Vec::new() };
+        distiller.add_triples(summary, &mut writer, &mut context).unwrap();
+        writer.edges
+    }
+
+    fn mondo(id: u32) -> Iri {
+        penyu::vocabs::obo::Ontology::MONDO.create_iri(id)
+    }
+
+    fn record(disease: &str, related: &str, score: f64) -> Value {
+        json!({ "disease": disease, "related": { "id": related }, "score": score })
+    }
+
+    #[test]
+    fn records_become_scored_edges() {
+        let distiller = disease_spec(json!({}));
+        let summary = summarize(&distiller, &[record("MONDO:0005148", "MONDO:0005015", 0.8)]);
+        let edges = distill(&distiller, summary);
+        assert_eq!(edges.len(), 1);
+        let (subject, predicate, object, evidence) = &edges[0];
+        assert!(*subject == mondo(5148) && *object == mondo(5015));
+        assert!(*predicate == Predicates::AssociatedWith);
+        assert_eq!(evidence.source(), "test");
+        assert_eq!(evidence.score_value(), Some(0.8));
+        let records: Vec<String> = evidence.records().iter().map(ToString::to_string).collect();
+        assert_eq!(records, vec!["1:1"]);
+    }
+
+    #[test]
+    fn filter_drops_records_before_dedup() {
+        let distiller = disease_spec(json!({ "filter": "score > 0.5" }));
+        let summary = summarize(&distiller, &[
+            record("MONDO:0005148", "MONDO:0005015", 0.8),
+            record("MONDO:0005148", "MONDO:0004975", 0.2),
+        ]);
+        let counts = distiller.count_records(&summary);
+        assert_eq!((counts.original, counts.filtered), (2, Some(1)));
+        let edges = distill(&distiller, summary);
+        assert_eq!(edges.len(), 1);
+        assert!(edges[0].2 == mondo(5015));
+    }
+
+    #[test]
+    fn dedup_keeps_first_evidence_and_all_records() {
+        let distiller = disease_spec(json!({ "dedup": ["disease", "related.id"] }));
+        let summary = summarize(&distiller, &[
+            record("MONDO:0005148", "MONDO:0005015", 0.8),
+            record("MONDO:0005148", "MONDO:0005015", 0.3),
+            record("MONDO:0005148", "MONDO:0004975", 0.5),
+        ]);
+        assert_eq!(distiller.count_records(&summary).deduplicated, 2);
+        let edges = distill(&distiller, summary);
+        let (_, _, _, evidence) = edges.iter()
+            .find(|(_, _, object, _)| *object == mondo(5015)).unwrap();
+        assert_eq!(evidence.score_value(), Some(0.8));
+        let records: Vec<String> = evidence.records().iter().map(ToString::to_string).collect();
+        assert_eq!(records, vec!["1:1", "1:2"]);
+    }
+
+    #[test]
+    fn missing_subject_is_an_error() {
+        let distiller = disease_spec(json!({}));
+        let line = json!({ "related": { "id": "MONDO:0005015" }, "score": 0.8 }).to_string();
+        assert!(distiller.new_summary().next(line).is_err());
+    }
+
+    fn spec_with_predicate(predicate: Value) -> Result<GenericSpec, Error> {
+        let spec = json!({
+            "subject": { "field": "gene", "concept": "gene" },
+            "object": { "field": "tissue", "concept": "tissue" },
+            "predicate": predicate,
+            "evidence": [],
+        });
+        GenericSpec::from_json(spec.as_object().unwrap())
+    }
+
+    #[test]
+    fn known_predicates_are_found_by_name_or_curie() {
+        for name in ["over_expressed_in", "RO:0002245"] {
+            let spec = spec_with_predicate(json!(name)).unwrap();
+            assert!(spec.predicate == Predicates::OverExpressedIn);
+        }
+    }
+
+    #[test]
+    fn unknown_predicates_point_to_defining_one() {
+        let error = spec_with_predicate(json!("RO:0002206")).err().unwrap().to_string();
+        assert!(error.contains("Other predicates can be defined"), "{}", error);
+    }
+
+    #[test]
+    fn defined_predicates_take_domain_and_range_from_concepts() {
+        let spec = spec_with_predicate(json!({
+            "iri": "http://purl.obolibrary.org/obo/RO_0002206",
+            "curie": "RO:0002206",
+            "label": "expressed in",
+            "biolink": "biolink:expressed_in",
+            "inverse": "biolink:expresses",
+        })).unwrap();
+        let def = spec.predicate.def();
+        assert_eq!(def.label, "expressed in");
+        assert!(def.domain == [Concepts::Gene] && def.range == [Concepts::Tissue]);
+        assert_eq!(spec.predicate.iri().to_string(), "http://purl.obolibrary.org/obo/RO_0002206");
+    }
+
+    #[test]
+    fn defined_predicates_may_not_shadow_known_ones() {
+        let result = spec_with_predicate(json!({
+            "iri": "http://example.org/over_expressed_in",
+            "curie": "RO:0002245",
+            "label": "over-expressed in",
+            "biolink": "biolink:expressed_in",
+            "inverse": "biolink:expresses",
+        }));
+        assert!(result.is_err());
+    }
+}
@@ -1,44 +1,78 @@
-use crate::data::sources;
+use crate::data::SourceDef;
+use crate::distill::correction::Correction;
+use crate::distill::distiller::{self, DistillContext, Distiller};
+use crate::distill::evidence::{Evidence, RecordId, ScoreDirection};
+use crate::distill::mappers::MapperKind;
+use crate::distill::report::RecordCounts;
 use crate::distill::util;
 use crate::distill::util::{pretty_f64, OrdF64};
 use crate::distill::write::GraphWriter;
 use crate::error::Error;
-use crate::mapper::tissues::TissueMapper;
-use crate::mapper::track::Tracker;
+use crate::location::DataLocation;
 use crate::pipe::{LinePipe, NextSummary, Summary};
 use crate::runtime::Runtime;
-use crate::s3::S3Uri;
-use crate::vocabs::Concepts;
-use crate::{distill, json, s3};
-use std::collections::BTreeSet;
-
-pub(crate) fn report_gtex_sldsc(runtime: &Runtime) -> Result<usize, Error> {
-    println!("From the GTEx SLDSC data:");
-    let summary = distill_gtex_sldsc(runtime)?;
-    println!("Original records: {}", summary.n_original);
-    println!("Filtered records: {}", summary.n_filtered);
-    println!(
-        "Assertions: biosample - enriched for - mondo id ({})",
-        summary.mondo_id_tissues.len()
-    );
-    Ok(summary.mondo_id_tissues.len())
-}
-
-pub(crate) fn distill_gtex_sldsc(runtime: &Runtime) -> Result<GtexSldscSummary, Error> {
-    let s3uri = sources::GTEX_SLSDC.to_s3uri();
-    let pipe = GtexSldscPipe::new(s3uri);
-    let summary = s3::process(runtime, &pipe)?;
+use crate::vocabs::{Concepts, Predicates};
+use crate::{distill, json, location};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+pub(crate) const NAME: &str = "gtex_sldsc";
+
+pub(crate) mod assertions {
+    pub(crate) const DISEASE_LOCATION: &str = "disease_location";
+}
+
+const DEFAULT_ALPHA: f64 = 0.05;
+const DEFAULT_MIN_ENRICHMENT: f64 = 1.0;
+
+mod keys {
+    pub(crate) const CORRECTION: &str = "correction";
+    pub(crate) const SCOPE: &str = "scope";
+    pub(crate) const ALPHA: &str = "alpha";
+    pub(crate) const MIN_ENRICHMENT: &str = "min_enrichment";
+}
+
+mod evidence_names {
+    pub(crate) const ENRICHMENT: &str = "enrichment";
+    pub(crate) const P_VALUE: &str = "p_value";
+    pub(crate) const Q_VALUE: &str = "q_value";
+}
+
+mod scopes {
+    pub(crate) const ALL: &str = "all";
+    pub(crate) const PHENOTYPE: &str = "phenotype";
+    pub(crate) const KNOWN: [&str; 2] = [ALL, PHENOTYPE];
+}
+
+pub(crate) struct GtexSldscDistiller {
+    correction: Correction,
+    scope: CorrectionScope,
+    alpha: f64,
+    min_enrichment: f64,
+}
+
+#[derive(Clone, Copy)]
+enum CorrectionScope {
+    All,
+    Phenotype,
+}
+
+pub(crate) fn distill_gtex_sldsc(runtime: &Runtime, location: &DataLocation)
+    -> Result<GtexSldscSummary, Error> {
+    let pipe = GtexSldscPipe::new(location.clone());
+    let summary = location::process(runtime, &pipe)?;
     Ok(summary)
 }
 
 pub(crate) struct GtexSldscPipe {
-    s3uri: S3Uri,
+    location: DataLocation,
 }
 
 pub(crate) struct GtexSldscSummary {
     n_original: usize,
-    n_filtered: usize,
-    mondo_id_tissues: BTreeSet<MondoIdTissue>,
+    position: RecordId,
+    mondo_id_tissues: BTreeMap<MondoIdTissue, Vec<RecordId>>,
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq)]
@@ -54,12 +88,95 @@ impl GtexSldscSummary {
     pub(crate) fn new() -> GtexSldscSummary {
         GtexSldscSummary {
             n_original: 0,
-            n_filtered: 0,
-            mondo_id_tissues: BTreeSet::new(),
+            position: RecordId::new(),
+            mondo_id_tissues: BTreeMap::new(),
+        }
+    }
+}
+
+impl TryFrom<&str> for CorrectionScope {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            scopes::ALL => Ok(CorrectionScope::All),
+            scopes::PHENOTYPE => Ok(CorrectionScope::Phenotype),
+            _ => Err(Error::from(format!(
+                "Unknown correction scope '{}'. Known correction scopes are '{}'.", string,
+                scopes::KNOWN.join("', '")
+            ))),
+        }
+    }
+}
+
+impl Display for CorrectionScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CorrectionScope::All => write!(f, "all records"),
+            CorrectionScope::Phenotype => write!(f, "each phenotype"),
         }
     }
 }
 
+impl GtexSldscDistiller {
+    pub(crate) fn new(config: Option<&Map<String, Value>>) -> Result<GtexSldscDistiller, Error> {
+        let mut distiller = GtexSldscDistiller {
+            correction: Correction::BenjaminiHochberg,
+            scope: CorrectionScope::All,
+            alpha: DEFAULT_ALPHA,
+            min_enrichment: DEFAULT_MIN_ENRICHMENT,
+        };
+        if let Some(config) = config {
+            if config.contains_key(keys::CORRECTION) {
+                let correction = json::get_string(config, keys::CORRECTION)?;
+                distiller.correction = Correction::try_from(correction.as_str())?;
+            }
+            if config.contains_key(keys::SCOPE) {
+                let scope = json::get_string(config, keys::SCOPE)?;
+                distiller.scope = CorrectionScope::try_from(scope.as_str())?;
+            }
+            if config.contains_key(keys::ALPHA) {
+                distiller.alpha = json::get_number(config, keys::ALPHA)?;
+            }
+            if config.contains_key(keys::MIN_ENRICHMENT) {
+                distiller.min_enrichment = json::get_number(config, keys::MIN_ENRICHMENT)?;
+            }
+        }
+        Ok(distiller)
+    }
+    /// Records above the enrichment threshold, before multiple testing correction.
+    fn count_enriched(&self, summary: &GtexSldscSummary) -> usize {
+        summary.mondo_id_tissues.iter()
+            .filter(|(record, _)| record.enrichment.value > self.min_enrichment)
+            .map(|(_, records)| records.len()).sum()
+    }
+    fn select(&self, summary: GtexSldscSummary) -> Vec<(MondoIdTissue, Vec<RecordId>, f64)> {
+        let mut groups: BTreeMap<Option<u32>, Vec<(MondoIdTissue, Vec<RecordId>)>> =
+            BTreeMap::new();
+        for (mondo_id_tissue, records) in summary.mondo_id_tissues {
+            let group = match self.scope {
+                CorrectionScope::All => None,
+                CorrectionScope::Phenotype => Some(mondo_id_tissue.mondo_id),
+            };
+            groups.entry(group).or_default().push((mondo_id_tissue, records));
+        }
+        let mut selected: Vec<(MondoIdTissue, Vec<RecordId>, f64)> = Vec::new();
+        for mondo_id_tissues in groups.into_values() {
+            let p_values: Vec<f64> =
+                mondo_id_tissues.iter().map(|(record, _)| record.p_value.value).collect();
+            let q_values = self.correction.adjust(&p_values);
+            for ((mondo_id_tissue, records), q_value) in
+                mondo_id_tissues.into_iter().zip(q_values) {
+                if q_value < self.alpha && mondo_id_tissue.enrichment.value > self.min_enrichment
+                {
+                    selected.push((mondo_id_tissue, records, q_value));
+                }
+            }
+        }
+        selected
+    }
+}
+
 impl Summary for GtexSldscSummary {
     fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
         let json_obj = json::as_json_obj(&line)?;
@@ -71,43 +188,47 @@ impl Summary for GtexSldscSummary {
         let p_value = json::get_number(&json_obj, "pValue")?;
         let GtexSldscSummary {
             mut n_original,
-            mut n_filtered,
+            position,
             mut mondo_id_tissues,
         } = self;
         n_original += 1;
-        if p_value < 0.05 && enrichment > 1.0 {
-            n_filtered += 1;
-            let enrichment = OrdF64::new(enrichment);
-            let p_value = OrdF64::new(p_value);
-            mondo_id_tissues.insert(MondoIdTissue {
-                mondo_id,
-                tissue,
-                phenotype,
-                enrichment,
-                p_value,
-            });
-        }
+        let position = position.next_line();
+        let enrichment = OrdF64::new(enrichment);
+        let p_value = OrdF64::new(p_value);
+        mondo_id_tissues.entry(MondoIdTissue {
+            mondo_id,
+            tissue,
+            phenotype,
+            enrichment,
+            p_value,
+        }).or_default().push(position);
         Ok(NextSummary {
             summary: GtexSldscSummary {
                 n_original,
-                n_filtered,
+                position,
                 mondo_id_tissues,
             },
         })
     }
+    fn start_object(self, index: usize) -> Self {
+        GtexSldscSummary { position: self.position.start_object(index), ..self }
+    }
+    fn skip_line(self) -> Self {
+        GtexSldscSummary { position: self.position.next_line(), ..self }
+    }
 }
 
 impl GtexSldscPipe {
-    pub(crate) fn new(s3uri: S3Uri) -> GtexSldscPipe {
-        GtexSldscPipe { s3uri }
+    pub(crate) fn new(location: DataLocation) -> GtexSldscPipe {
+        GtexSldscPipe { location }
     }
 }
 
 impl LinePipe for GtexSldscPipe {
     type Summary = GtexSldscSummary;
 
-    fn s3uri(&self) -> &S3Uri {
-        &self.s3uri
+    fn location(&self) -> &DataLocation {
+        &self.location
     }
 
     fn new_summary(&self) -> GtexSldscSummary {
@@ -115,27 +236,106 @@ impl LinePipe for GtexSldscPipe {
     }
 }
 
-pub(crate) fn add_triples_gtex_sldsc<W: GraphWriter>(
-    writer: &mut W,
-    runtime: &Runtime,
-    tissue_mapper: &TissueMapper,
-    tissue_tracker: &mut Tracker,
-) -> Result<(), Error> {
-    let summary = distill_gtex_sldsc(runtime)?;
-    let disease_type = Concepts::Disease.concept_iri();
-    let tissue_type = Concepts::Tissue.concept_iri();
-    let disease_has_location = penyu::vocabs::obo::Ontology::RO.create_iri(4026);
-    for MondoIdTissue {
-        mondo_id, tissue, phenotype, enrichment, p_value
-    } in summary.mondo_id_tissues {
-        let mondo_iri = penyu::vocabs::obo::Ontology::MONDO.create_iri(mondo_id);
-        writer.add_node(&mondo_iri, disease_type, &phenotype);
-        let tissue_iri = distill::get_tissue_iri(tissue_mapper, &tissue, tissue_tracker);
-        writer.add_node(&tissue_iri, tissue_type, &tissue);
-        let evidence_class =
-            format!("enrichment={},p_value={}", pretty_f64(enrichment.value),
-                    pretty_f64(p_value.value));
-        writer.add_edge(&mondo_iri, &disease_has_location, &tissue_iri, &evidence_class);
-    }
-    Ok(())
+impl Distiller for GtexSldscDistiller {
+    type Summary = GtexSldscSummary;
+    fn name(&self) -> &str { NAME }
+    fn assertion_types(&self) -> Vec<&str> { vec![assertions::DISEASE_LOCATION] }
+    fn required_mappers(&self) -> Vec<MapperKind> { vec![MapperKind::Tissue] }
+    fn new_summary(&self) -> GtexSldscSummary { GtexSldscSummary::new() }
+    fn summarize(&self, runtime: &Runtime, location: &DataLocation)
+                 -> Result<GtexSldscSummary, Error> {
+        distill_gtex_sldsc(runtime, location)
+    }
+    fn report_stats(&self, _source: &SourceDef, summary: GtexSldscSummary,
+                    assertions: &BTreeSet<String>) -> usize {
+        println!("From the GTEx SLDSC data:");
+        println!("Original records: {}", summary.n_original);
+        println!("Multiple testing: {} over {}, q < {}, enrichment > {}", self.correction,
+                 self.scope, pretty_f64(self.alpha), pretty_f64(self.min_enrichment));
+        println!("Records with enrichment > {}: {}", pretty_f64(self.min_enrichment),
+                 self.count_enriched(&summary));
+        let n_assertions = self.select(summary).len();
+        distiller::report_assertions(assertions, assertions::DISEASE_LOCATION,
+                                     "biosample - enriched for - mondo id", n_assertions)
+    }
+    fn count_records(&self, summary: &GtexSldscSummary) -> RecordCounts {
+        RecordCounts {
+            original: summary.n_original,
+            filtered: Some(self.count_enriched(summary)),
+            deduplicated: summary.mondo_id_tissues.len(),
+        }
+    }
+    fn add_triples(&self, summary: GtexSldscSummary, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<(), Error> {
+        let tissue_mapper = context.mappers_chest.get_tissue_mapper()?;
+        let source = context.source;
+        let tissue_tracker = &mut context.trackers.tissue;
+        let disease_type = Concepts::Disease.concept_iri();
+        let tissue_type = Concepts::Tissue.concept_iri();
+        for (MondoIdTissue {
+            mondo_id, tissue, phenotype, enrichment, p_value
+        }, records, q_value) in self.select(summary) {
+            let mondo_iri = penyu::vocabs::obo::Ontology::MONDO.create_iri(mondo_id);
+            writer.add_node(&mondo_iri, disease_type, &phenotype);
+            let tissue_iri = distill::get_tissue_iri(tissue_mapper, &tissue, tissue_tracker);
+            writer.add_node(&tissue_iri, tissue_type, &tissue);
+            let mut evidence = Evidence::new(source);
+            evidence.add_number(evidence_names::ENRICHMENT, enrichment.value);
+            evidence.add_number(evidence_names::P_VALUE, p_value.value);
+            if matches!(self.correction, Correction::None) {
+                evidence.set_score(evidence_names::P_VALUE, ScoreDirection::LowerIsBetter);
+            } else {
+                evidence.add_number(evidence_names::Q_VALUE, q_value);
+                evidence.set_score(evidence_names::Q_VALUE, ScoreDirection::LowerIsBetter);
+            }
+            evidence.set_records(records);
+            writer.add_edge(&mondo_iri, Predicates::DiseaseHasLocation, &tissue_iri, &evidence);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn summarize(records: &[(&str, &str, f64)]) -> GtexSldscSummary {
+        let mut summary = GtexSldscSummary::new();
+        for (mondo_id, biosample, p_value) in records {
+            let line = json!({
+                "mondo_id": mondo_id, "biosample": biosample, "phenotype": mondo_id,
+                "enrichment": 2.0, "pValue": p_value
+            });
+            summary = summary.next(line.to_string()).unwrap().summary;
+        }
+        summary
+    }
+
+    fn selected(scope: &str) -> Vec<(u32, String)> {
+        let config = json!({ "correction": "bh", "scope": scope });
+        let distiller = GtexSldscDistiller::new(config.as_object()).unwrap();
+        let summary = summarize(&[
+            ("MONDO:0000001", "liver", 0.01), ("MONDO:0000001", "lung", 0.02),
+            ("MONDO:0000002", "liver", 0.03), ("MONDO:0000002", "lung", 0.9),
+        ]);
+        distiller.select(summary).into_iter()
+            .map(|(record, _, _)| (record.mondo_id, record.tissue)).collect()
+    }
+
+    #[test]
+    fn bh_over_all_records_pools_phenotypes() {
+        // q-values over all four tests are 0.04, 0.04, 0.04 and 0.9.
+        assert_eq!(selected("all"), vec![
+            (1, "liver".to_string()), (1, "lung".to_string()), (2, "liver".to_string())
+        ]);
+    }
+
+    #[test]
+    fn bh_per_phenotype_corrects_each_phenotype_separately() {
+        // For MONDO:0000002, q-values over its two tests are 0.06 and 0.9.
+        assert_eq!(selected("phenotype"), vec![
+            (1, "liver".to_string()), (1, "lung".to_string())
+        ]);
+    }
 }
@@ -1,73 +1,133 @@
-use crate::data::sources;
+use crate::data::SourceDef;
+use crate::distill::distiller::{self, DistillContext, Distiller};
+use crate::distill::evidence::{Evidence, RecordId, ScoreDirection};
+use crate::distill::mappers::MapperKind;
+use crate::distill::report::RecordCounts;
 use crate::distill::write::GraphWriter;
 use crate::error::Error;
-use crate::mapper::hgnc::GeneMapper;
-use crate::mapper::tissues::TissueMapper;
-use crate::mapper::track::Tracker;
+use crate::location::DataLocation;
 use crate::pipe::{LinePipe, NextSummary, Summary};
 use crate::runtime::Runtime;
-use crate::s3::S3Uri;
-use crate::vocabs::Concepts;
-use crate::{distill, json, s3};
-use std::cmp::max;
-use std::collections::BTreeMap;
+use crate::vocabs::{Concepts, Predicates};
+use crate::{distill, json, location};
+use serde_json::{Map, Value};
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, BTreeSet};
 use crate::distill::util::pretty_f64;
 
-pub(crate) fn report_gtex_tstat(runtime: &Runtime) -> Result<usize, Error> {
-    println!("From the GTEx tstat data:");
-    let summary_raw = distill_gtex_tstat(runtime)?;
-    println!("Original records: {}", summary_raw.n_original);
-    println!("Deduplicated records: {}", summary_raw.count_assertions());
-    let summary = summary_raw.only_keep_tenth();
-    println!("Assertions: gene - specifically expressed in - biosample ({})",
-             summary.count_assertions());
-    Ok(summary.count_assertions())
-}
-
-pub(crate) fn distill_gtex_tstat(runtime: &Runtime) -> Result<GtexTstatSummary, Error> {
-    let s3uri = sources::GTEX_TSTAT.to_s3uri();
-    let pipe = GtexTstatPipe::new(s3uri);
-    let summary = s3::process(runtime, &pipe)?;
+pub(crate) const NAME: &str = "gtex_tstat";
+
+pub(crate) mod assertions {
+    pub(crate) const OVER_EXPRESSED_IN: &str = "over_expressed_in";
+    pub(crate) const UNDER_EXPRESSED_IN: &str = "under_expressed_in";
+}
+
+mod evidence_names {
+    pub(crate) const TSTAT: &str = "tstat";
+}
+
+const DEFAULT_TAIL_FRACTION: f64 = 0.1;
+
+mod keys {
+    pub(crate) const TAIL_FRACTION: &str = "tail_fraction";
+    pub(crate) const BOTTOM_TAIL: &str = "bottom_tail";
+}
+
+pub(crate) struct GtexTstatDistiller {
+    tail_fraction: f64,
+    with_bottom_tail: bool,
+}
+
+pub(crate) fn distill_gtex_tstat(runtime: &Runtime, location: &DataLocation)
+    -> Result<GtexTstatSummary, Error> {
+    let pipe = GtexTstatPipe::new(location.clone());
+    let summary = location::process(runtime, &pipe)?;
     Ok(summary)
 }
 
 struct GeneTstat {
     gene: String,
     tstat: f64,
+    record: RecordId,
 }
 pub(crate) struct GtexTstatSummary {
     n_original: u64,
+    position: RecordId,
     biosample_to_genes: BTreeMap<String, Vec<GeneTstat>>,
 }
 
+pub(crate) struct GtexTstatTails {
+    top: BTreeMap<String, Vec<GeneTstat>>,
+    bottom: BTreeMap<String, Vec<GeneTstat>>,
+}
+
 pub(crate) struct GtexTstatPipe {
-    s3uri: S3Uri,
+    location: DataLocation,
 }
 impl GtexTstatSummary {
     pub(crate) fn new() -> GtexTstatSummary {
         let n_original: u64 = 0;
         let biosample_to_genes: BTreeMap<String, Vec<GeneTstat>> = BTreeMap::new();
-        GtexTstatSummary { n_original, biosample_to_genes }
+        GtexTstatSummary { n_original, position: RecordId::new(), biosample_to_genes }
     }
-    pub(crate) fn only_keep_tenth(self) -> GtexTstatSummary {
-        let GtexTstatSummary {
-            n_original, mut biosample_to_genes
-        } = self;
-        for gene_tstat_list in biosample_to_genes.values_mut() {
+    pub(crate) fn select_tails(self, fraction: f64, with_bottom: bool) -> GtexTstatTails {
+        let mut top: BTreeMap<String, Vec<GeneTstat>> = BTreeMap::new();
+        let mut bottom: BTreeMap<String, Vec<GeneTstat>> = BTreeMap::new();
+        for (biosample, mut gene_tstat_list) in self.biosample_to_genes {
             gene_tstat_list.retain(|gene_tstat: &GeneTstat| !gene_tstat.tstat.is_nan());
             gene_tstat_list.sort_by(
                 |a, b| a.tstat.partial_cmp(&b.tstat).unwrap().reverse()
             );
-            let len_new = max((gene_tstat_list.len() + 5) / 10, 1);
-            gene_tstat_list.truncate(len_new);
+            let len_tail = max(((gene_tstat_list.len() as f64) * fraction).round() as usize, 1);
+            let len_top = min(len_tail, gene_tstat_list.len());
+            let rest = gene_tstat_list.split_off(len_top);
+            if with_bottom {
+                let bottom_tail: Vec<GeneTstat> =
+                    rest.into_iter().rev().take_while(|gene_tstat| gene_tstat.tstat < 0.0)
+                        .take(len_tail).collect();
+                if !bottom_tail.is_empty() {
+                    bottom.insert(biosample.clone(), bottom_tail);
+                }
+            }
+            top.insert(biosample, gene_tstat_list);
         }
-        GtexTstatSummary { n_original, biosample_to_genes }
+        GtexTstatTails { top, bottom }
     }
     pub(crate) fn count_assertions(&self) -> usize {
-        self.biosample_to_genes.values().map(|v| v.len()).sum()
+        count_gene_tstats(&self.biosample_to_genes)
+    }
+}
+
+impl GtexTstatDistiller {
+    pub(crate) fn new(config: Option<&Map<String, Value>>) -> Result<GtexTstatDistiller, Error> {
+        let mut distiller =
+            GtexTstatDistiller { tail_fraction: DEFAULT_TAIL_FRACTION, with_bottom_tail: false };
+        if let Some(config) = config {
+            if config.contains_key(keys::TAIL_FRACTION) {
+                let tail_fraction = json::get_number(config, keys::TAIL_FRACTION)?;
+                if !(tail_fraction > 0.0 && tail_fraction <= 0.5) {
+                    return Err(Error::from(format!(
+                        "Field '{}' needs to be greater than 0 and at most 0.5, but is {}.",
+                        keys::TAIL_FRACTION, tail_fraction
+                    )));
+                }
+                distiller.tail_fraction = tail_fraction;
+            }
+            if config.contains_key(keys::BOTTOM_TAIL) {
+                distiller.with_bottom_tail = json::get_bool(config, keys::BOTTOM_TAIL)?;
+            }
+        }
+        Ok(distiller)
+    }
+    fn select_tails(&self, summary: GtexTstatSummary) -> GtexTstatTails {
+        summary.select_tails(self.tail_fraction, self.with_bottom_tail)
     }
 }
 
+fn count_gene_tstats(biosample_to_genes: &BTreeMap<String, Vec<GeneTstat>>) -> usize {
+    biosample_to_genes.values().map(|v| v.len()).sum()
+}
+
 impl Summary for GtexTstatSummary {
     fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
         let json_obj = json::as_json_obj(&line)?;
@@ -75,50 +135,125 @@ impl Summary for GtexTstatSummary {
         let gene = json::get_string(&json_obj, "gene")?;
         let tstat = json::get_number(&json_obj, "tstat")?;
         let GtexTstatSummary {
-            mut n_original, mut biosample_to_genes
+            mut n_original, position, mut biosample_to_genes
         } = self;
         n_original += 1;
+        let record = position.next_line();
         match biosample_to_genes.get_mut(&biosample) {
             None => {
-                let genes = vec![GeneTstat { gene, tstat }];
+                let genes = vec![GeneTstat { gene, tstat, record }];
                 biosample_to_genes.insert(biosample, genes);
             }
             Some(gene_tstat_list) => {
-                gene_tstat_list.push(GeneTstat { gene, tstat });
+                gene_tstat_list.push(GeneTstat { gene, tstat, record });
             }
         };
-        Ok(NextSummary { summary: GtexTstatSummary { n_original, biosample_to_genes } })
+        Ok(NextSummary {
+            summary: GtexTstatSummary { n_original, position: record, biosample_to_genes }
+        })
+    }
+    fn start_object(self, index: usize) -> Self {
+        GtexTstatSummary { position: self.position.start_object(index), ..self }
+    }
+    fn skip_line(self) -> Self {
+        GtexTstatSummary { position: self.position.next_line(), ..self }
     }
 }
 
 impl GtexTstatPipe {
-    pub(crate) fn new(s3uri: S3Uri) -> GtexTstatPipe { GtexTstatPipe { s3uri } }
+    pub(crate) fn new(location: DataLocation) -> GtexTstatPipe { GtexTstatPipe { location } }
 }
 
 impl LinePipe for GtexTstatPipe {
     type Summary = GtexTstatSummary;
-    fn s3uri(&self) -> &S3Uri { &self.s3uri }
+    fn location(&self) -> &DataLocation { &self.location }
     fn new_summary(&self) -> Self::Summary { GtexTstatSummary::new() }
 }
 
-pub(crate) fn add_triples_gtex_tstat<W: GraphWriter>(writer: &mut W, runtime: &Runtime,
-                                     gene_mapper: &GeneMapper, tissue_mapper: &TissueMapper,
-                                     gene_tracker: &mut Tracker, tissue_tracker: &mut Tracker)
-                                     -> Result<(), Error> {
-    let summary = distill_gtex_tstat(runtime)?;
-    let biosample_type = Concepts::Tissue.concept_iri();
-    let gene_type = Concepts::Gene.concept_iri();
-    let over_expressed_in = penyu::vocabs::obo::Ontology::RO.create_iri(2245);
-    for (biosample, gene_tstat_list) in summary.biosample_to_genes.iter() {
-        let biosample_iri = distill::get_tissue_iri(tissue_mapper, biosample, tissue_tracker);
-        writer.add_node(&biosample_iri, biosample_type, biosample);
-        for gene_tstat in gene_tstat_list {
-            let gene_iri = distill::get_gene_iri(gene_mapper, &gene_tstat.gene, gene_tracker);
-            writer.add_node(&gene_iri, gene_type, &gene_tstat.gene);
-            let evidence = format!("tstat={}", pretty_f64(gene_tstat.tstat));
-            writer.add_edge(&biosample_iri, &over_expressed_in, &gene_iri, &evidence);
+impl Distiller for GtexTstatDistiller {
+    type Summary = GtexTstatSummary;
+    fn name(&self) -> &str { NAME }
+    fn assertion_types(&self) -> Vec<&str> {
+        if self.with_bottom_tail {
+            vec![assertions::OVER_EXPRESSED_IN, assertions::UNDER_EXPRESSED_IN]
+        } else {
+            vec![assertions::OVER_EXPRESSED_IN]
         }
     }
-    Ok(())
+    fn required_mappers(&self) -> Vec<MapperKind> { vec![MapperKind::Tissue, MapperKind::Gene] }
+    fn new_summary(&self) -> GtexTstatSummary { GtexTstatSummary::new() }
+    fn summarize(&self, runtime: &Runtime, location: &DataLocation)
+                 -> Result<GtexTstatSummary, Error> {
+        distill_gtex_tstat(runtime, location)
+    }
+    fn report_stats(&self, _source: &SourceDef, summary: GtexTstatSummary,
+                    assertions: &BTreeSet<String>) -> usize {
+        println!("From the GTEx tstat data:");
+        println!("Original records: {}", summary.n_original);
+        println!("Deduplicated records: {}", summary.count_assertions());
+        let tails = self.select_tails(summary);
+        let n_top = count_gene_tstats(&tails.top);
+        println!("Top tail per biosample: {}%", pretty_f64(100.0 * self.tail_fraction));
+        let n_top = distiller::report_assertions(
+            assertions, assertions::OVER_EXPRESSED_IN,
+            "gene - specifically expressed in - biosample", n_top
+        );
+        if !self.with_bottom_tail {
+            return n_top;
+        }
+        let n_bottom = count_gene_tstats(&tails.bottom);
+        println!("Bottom tail per biosample: {}%, negative tstat only",
+                 pretty_f64(100.0 * self.tail_fraction));
+        let n_bottom = distiller::report_assertions(
+            assertions, assertions::UNDER_EXPRESSED_IN, "gene - under-expressed in - biosample",
+            n_bottom
+        );
+        n_top + n_bottom
+    }
+    fn count_records(&self, summary: &GtexTstatSummary) -> RecordCounts {
+        RecordCounts {
+            original: summary.n_original as usize,
+            filtered: None,
+            deduplicated: summary.count_assertions(),
+        }
+    }
+    fn add_triples(&self, summary: GtexTstatSummary, writer: &mut dyn GraphWriter,
+                   context: &mut DistillContext) -> Result<(), Error> {
+        let tissue_mapper = context.mappers_chest.get_tissue_mapper()?;
+        let gene_mapper = context.mappers_chest.get_gene_mapper()?;
+        let with_top = context.includes(assertions::OVER_EXPRESSED_IN);
+        let with_bottom = context.includes(assertions::UNDER_EXPRESSED_IN);
+        let source = context.source;
+        let trackers = &mut context.trackers;
+        let biosample_type = Concepts::Tissue.concept_iri();
+        let gene_type = Concepts::Gene.concept_iri();
+        let tails = self.select_tails(summary);
+        let mut tails_predicates = Vec::new();
+        if with_top {
+            let direction = ScoreDirection::HigherIsBetter;
+            tails_predicates.push((&tails.top, Predicates::OverExpressedIn, direction));
+        }
+        if with_bottom {
+            let direction = ScoreDirection::LowerIsBetter;
+            tails_predicates.push((&tails.bottom, Predicates::UnderExpressedIn, direction));
+        }
+        for (biosample_to_genes, predicate, direction) in tails_predicates {
+            for (biosample, gene_tstat_list) in biosample_to_genes.iter() {
+                let biosample_iri =
+                    distill::get_tissue_iri(tissue_mapper, biosample, &mut trackers.tissue);
+                writer.add_node(&biosample_iri, biosample_type, biosample);
+                for gene_tstat in gene_tstat_list {
+                    let gene_iri =
+                        distill::get_gene_iri(gene_mapper, &gene_tstat.gene, &mut trackers.gene);
+                    writer.add_node(&gene_iri, gene_type, &gene_tstat.gene);
+                    let mut evidence = Evidence::new(source);
+                    evidence.add_number(evidence_names::TSTAT, gene_tstat.tstat);
+                    evidence.set_score(evidence_names::TSTAT, direction);
+                    evidence.set_records(vec![gene_tstat.record]);
+                    writer.add_edge(&gene_iri, predicate, &biosample_iri, &evidence);
+                }
+            }
+        }
+        Ok(())
+    }
 }
-
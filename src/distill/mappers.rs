@@ -3,9 +3,18 @@ use crate::mapper::files::VocabFiles;
 use crate::mapper::{clingen, hgnc};
 use crate::mapper::hgnc::{GeneMapper, Mappers, ProteinMapper};
 use crate::mapper::tissues::TissueMapper;
+use penyu::model::iri::Iri;
 use std::cell::OnceCell;
 use crate::mapper::variants::VariantMapper;
 
+#[derive(Copy, Clone)]
+pub(crate) enum MapperKind {
+    Tissue,
+    Gene,
+    Protein,
+    Variant,
+}
+
 pub(crate) struct MappersChest {
     vocab_files: VocabFiles,
     tissue_mapper: OnceCell<Result<TissueMapper, Error>>,
@@ -21,6 +30,15 @@ impl MappersChest {
         let variant_mapper: OnceCell<Result<VariantMapper, Error>> = OnceCell::new();
         Ok(MappersChest { vocab_files, tissue_mapper, gene_protein_mappers, variant_mapper })
     }
+    pub(crate) fn load(&self, kind: MapperKind) -> Result<(), Error> {
+        match kind {
+            MapperKind::Tissue => { self.get_tissue_mapper()?; }
+            MapperKind::Gene => { self.get_gene_mapper()?; }
+            MapperKind::Protein => { self.get_protein_mapper()?; }
+            MapperKind::Variant => { self.get_variant_mapper()?; }
+        }
+        Ok(())
+    }
     pub(crate) fn get_tissue_mapper(&self) -> Result<&TissueMapper, Error> {
         let result = self.tissue_mapper.get_or_init(|| {
             self.vocab_files.get_tissue_mapper()
@@ -45,6 +63,17 @@ impl MappersChest {
             Err(error) => Err(error.approximate_clone())
         }
     }
+    /// The label of an ontology term or HGNC gene, from mappers that are already loaded.
+    pub(crate) fn ontology_label(&self, iri: &Iri) -> Option<&str> {
+        if let Some(Ok(mappers)) = self.gene_protein_mappers.get()
+            && let Some(label) = mappers.gene_mapper.label(iri) {
+            return Some(label);
+        }
+        match self.tissue_mapper.get() {
+            Some(Ok(tissue_mapper)) => tissue_mapper.label(iri),
+            _ => None,
+        }
+    }
     pub(crate) fn get_variant_mapper(&self) -> Result<&VariantMapper, Error> {
         let result = self.variant_mapper.get_or_init(|| {
             clingen::get_variant_mapper(&self.vocab_files.variant_file())
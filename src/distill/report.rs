@@ -0,0 +1,140 @@
+use crate::data::SourceDef;
+use crate::distill::write::labels::LabelConflicts;
+use crate::error::Error;
+use crate::io;
+use crate::mapper::track::Tracker;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+
+pub(crate) const REPORT_FILE: &str = "run_report.json";
+
+/// Record counts of one source, before triples are added. Filtered is only known for
+/// distillers that filter records one by one.
+pub(crate) struct RecordCounts {
+    pub(crate) original: usize,
+    pub(crate) filtered: Option<usize>,
+    pub(crate) deduplicated: usize,
+}
+
+struct SourceReport {
+    name: String,
+    distiller: String,
+    release: String,
+    counts: RecordCounts,
+    emitted: usize,
+    duration: Duration,
+}
+
+pub(crate) struct RunReport {
+    sources: Vec<SourceReport>,
+    mappers: Vec<Value>,
+    nodes: BTreeMap<String, usize>,
+    edges: BTreeMap<String, usize>,
+    n_nodes_without_id: usize,
+    n_edges_with_multiple_sources: usize,
+    label_conflicts: LabelConflicts,
+    warnings: Vec<String>,
+    duration: Duration,
+}
+
+impl RunReport {
+    pub(crate) fn new() -> RunReport {
+        RunReport {
+            sources: Vec::new(),
+            mappers: Vec::new(),
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            n_nodes_without_id: 0,
+            n_edges_with_multiple_sources: 0,
+            label_conflicts: LabelConflicts::new(),
+            warnings: Vec::new(),
+            duration: Duration::ZERO,
+        }
+    }
+    pub(crate) fn add_source(&mut self, source: &SourceDef, counts: RecordCounts, emitted: usize,
+                             duration: Duration) {
+        self.sources.push(SourceReport {
+            name: source.name.clone(),
+            distiller: source.distiller.name().to_string(),
+            release: source.release_label().to_string(),
+            counts,
+            emitted,
+            duration,
+        });
+    }
+    pub(crate) fn add_tracker(&mut self, tracker: &Tracker) {
+        let n_total = tracker.n_hit() + tracker.n_miss();
+        let hit_rate = if n_total == 0 { None } else {
+            Some(tracker.n_hit() as f64 / n_total as f64)
+        };
+        self.mappers.push(json!({
+            "name": tracker.name(),
+            "hits": tracker.n_hit(),
+            "misses": tracker.n_miss(),
+            "hit_rate": hit_rate,
+            "distinct_missing": tracker.n_missing(),
+        }));
+    }
+    pub(crate) fn set_graph_counts(&mut self, nodes: BTreeMap<String, usize>,
+                                   edges: BTreeMap<String, usize>, n_nodes_without_id: usize,
+                                   n_edges_with_multiple_sources: usize) {
+        self.nodes = nodes;
+        self.edges = edges;
+        self.n_nodes_without_id = n_nodes_without_id;
+        self.n_edges_with_multiple_sources = n_edges_with_multiple_sources;
+    }
+    pub(crate) fn set_label_conflicts(&mut self, label_conflicts: LabelConflicts) {
+        self.label_conflicts = label_conflicts;
+    }
+    pub(crate) fn add_warnings(&mut self, warnings: Vec<String>) {
+        self.warnings.extend(warnings);
+    }
+    pub(crate) fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+    fn to_json(&self) -> Value {
+        let sources: Vec<Value> = self.sources.iter().map(|source| {
+            json!({
+                "name": source.name,
+                "distiller": source.distiller,
+                "release": source.release,
+                "records": {
+                    "original": source.counts.original,
+                    "filtered": source.counts.filtered,
+                    "deduplicated": source.counts.deduplicated,
+                    "emitted": source.emitted,
+                },
+                "seconds": source.duration.as_secs_f64(),
+            })
+        }).collect();
+        let nodes: Map<String, Value> =
+            self.nodes.iter().map(|(class, count)| (class.clone(), json!(count))).collect();
+        let edges: Map<String, Value> =
+            self.edges.iter().map(|(predicate, count)| (predicate.clone(), json!(count)))
+                .collect();
+        json!({
+            "sources": sources,
+            "mappers": self.mappers,
+            "nodes": nodes,
+            "edges": edges,
+            "nodes_without_ddkg_id": self.n_nodes_without_id,
+            "edges_with_multiple_sources": self.n_edges_with_multiple_sources,
+            "label_conflicts": {
+                "nodes": self.label_conflicts.n_conflicts,
+                "examples": self.label_conflicts.examples,
+            },
+            "warnings": self.warnings,
+            "seconds": self.duration.as_secs_f64(),
+        })
+    }
+    pub(crate) fn write(&self, path: &Path) -> Result<(), Error> {
+        let writer = BufWriter::new(io::create_file(path)?);
+        serde_json::to_writer_pretty(writer, &self.to_json()).map_err(|error| {
+            Error::wrap(format!("Could not write report {}", path.to_string_lossy()), error)
+        })?;
+        Ok(())
+    }
+}
@@ -1,12 +1,72 @@
+use crate::data::SourceDef;
+use crate::distill::evidence::Evidence;
 use crate::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::distill::write::labels::LabelConflicts;
+use crate::location::ObjectInfo;
+use crate::vocabs::Predicates;
 use penyu::model::iri::Iri;
 
+pub(crate) mod count;
 pub(crate) mod turtle;
 pub(crate) mod ddkg;
+pub(crate) mod labels;
+pub(crate) mod merge;
+pub(crate) mod validate;
+
+#[derive(Clone, Copy)]
+pub(crate) enum PredicateStyle {
+    Curie,
+    Label,
+}
+
+mod predicate_styles {
+    pub(crate) const CURIE: &str = "curie";
+    pub(crate) const LABEL: &str = "label";
+    pub(crate) const ALL: [&str; 2] = [CURIE, LABEL];
+}
+
+impl TryFrom<&str> for PredicateStyle {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            predicate_styles::CURIE => Ok(PredicateStyle::Curie),
+            predicate_styles::LABEL => Ok(PredicateStyle::Label),
+            _ => Err(Error::from(format!(
+                "Unknown predicate style '{}'. Known predicate styles are '{}'.", string,
+                predicate_styles::ALL.join("', '")
+            ))),
+        }
+    }
+}
+
+impl Display for PredicateStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PredicateStyle::Curie => write!(f, "{}", predicate_styles::CURIE),
+            PredicateStyle::Label => write!(f, "{}", predicate_styles::LABEL),
+        }
+    }
+}
+
+impl PredicateStyle {
+    pub(crate) fn format(&self, predicate: Predicates) -> &'static str {
+        match self {
+            PredicateStyle::Curie => predicate.def().curie,
+            PredicateStyle::Label => predicate.def().label,
+        }
+    }
+}
 
 pub(crate) trait GraphWriter {
     fn add_node(&mut self, node: &Iri, class: &Iri, label: &str);
-    fn add_edge(&mut self, subject: &Iri, predicate: &Iri, object: &Iri,
-                _evidence_class: &str);
-    fn serialize(&self) -> Result<(), Error>;
+    fn add_ontology_label(&mut self, node: &Iri, label: &str);
+    fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                evidence: &Evidence);
+    fn add_source(&mut self, source: &SourceDef, objects: &[ObjectInfo]);
+    fn label_conflicts(&self) -> LabelConflicts;
+    /// Whether serializing writes the graph, rather than only describing it.
+    fn produces_output(&self) -> bool { true }
+    fn serialize(&mut self) -> Result<(), Error>;
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use crate::data::SourceDef;
+use crate::distill::evidence::Evidence;
+use crate::distill::write::ddkg;
+use crate::distill::write::labels::LabelConflicts;
+use crate::distill::write::validate::class_name;
+use crate::distill::write::GraphWriter;
+use crate::error::Error;
+use crate::location::ObjectInfo;
+use crate::vocabs::Predicates;
+use penyu::model::iri::Iri;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Writes nothing, but counts nodes per class and edges per predicate, for dry runs.
+pub(crate) struct CountingWriter {
+    node_classes: BTreeMap<Iri, BTreeSet<Iri>>,
+    edges: BTreeMap<Predicates, BTreeSet<(Iri, Iri)>>,
+    n_assertions: BTreeMap<Predicates, usize>,
+    sources: Vec<String>,
+}
+
+impl CountingWriter {
+    pub(crate) fn new() -> CountingWriter {
+        CountingWriter {
+            node_classes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            n_assertions: BTreeMap::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl GraphWriter for CountingWriter {
+    fn add_node(&mut self, node: &Iri, class: &Iri, _label: &str) {
+        self.node_classes.entry(node.clone()).or_default().insert(class.clone());
+    }
+    fn add_ontology_label(&mut self, _node: &Iri, _label: &str) {}
+    fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                _evidence: &Evidence) {
+        self.edges.entry(predicate).or_default().insert((subject.clone(), object.clone()));
+        *self.n_assertions.entry(predicate).or_insert(0) += 1;
+    }
+    fn add_source(&mut self, source: &SourceDef, _objects: &[ObjectInfo]) {
+        self.sources.push(source.name.clone());
+    }
+    fn label_conflicts(&self) -> LabelConflicts {
+        LabelConflicts::new()
+    }
+    fn produces_output(&self) -> bool { false }
+    fn serialize(&mut self) -> Result<(), Error> {
+        let mut n_nodes: BTreeMap<String, usize> = BTreeMap::new();
+        let mut n_unmapped: BTreeMap<String, usize> = BTreeMap::new();
+        let mut n_unmapped_total: usize = 0;
+        for (node, classes) in &self.node_classes {
+            let is_unmapped = ddkg::node_iri_to_id(node).is_none();
+            if is_unmapped {
+                n_unmapped_total += 1;
+            }
+            for class in classes {
+                *n_nodes.entry(class_name(class)).or_insert(0) += 1;
+                if is_unmapped {
+                    *n_unmapped.entry(class_name(class)).or_insert(0) += 1;
+                }
+            }
+        }
+        println!("Dry run of sources {}, nothing was written.", self.sources.join(", "));
+        println!("Nodes: {}", self.node_classes.len());
+        for (class, count) in &n_nodes {
+            println!("  {}: {} ({} without a DDKG id)", class, count,
+                     n_unmapped.get(class).copied().unwrap_or(0));
+        }
+        let n_edges: usize = self.edges.values().map(|edges| edges.len()).sum();
+        println!("Edges: {}", n_edges);
+        for (predicate, edges) in &self.edges {
+            println!("  {}: {} ({} assertions)", predicate.def().label, edges.len(),
+                     self.n_assertions[predicate]);
+        }
+        println!("Nodes without a DDKG id: {}", n_unmapped_total);
+        Ok(())
+    }
+}
@@ -1,4 +1,9 @@
-use crate::distill::write::GraphWriter;
+use crate::data::SourceDef;
+use crate::distill::evidence::{Evidence, EvidenceSelection};
+use crate::distill::write::labels::{LabelConflicts, LabelOrigin, NodeLabels};
+use crate::distill::write::merge::{self, MergePolicy};
+use crate::distill::write::{GraphWriter, PredicateStyle};
+use crate::distill::OutputOptions;
 use crate::error::Error;
 use penyu::model::iri::Iri;
 use std::collections::{BTreeMap, BTreeSet};
@@ -7,75 +12,166 @@ use std::path::{Path, PathBuf};
 use std::io::Write;
 use log::warn;
 use crate::io;
+use crate::location::ObjectInfo;
 use crate::mapper::clingen;
+use crate::vocabs::Predicates;
+use crate::tabular::escape_tsv;
 
 const NODES_FILE: &str = "nodes.tsv";
 const EDGES_FILE: &str = "edges.tsv";
 const UNMAPPED_FILE: &str = "unmapped";
-
-struct NodeProps {
-    label: String,
-}
+const SOURCES_FILE: &str = "sources.tsv";
+const SOURCE_OBJECTS_FILE: &str = "source_objects.tsv";
 
 #[derive(Eq, PartialEq, Ord, PartialOrd)]
 struct Edge {
     subject: Iri,
-    predicate: Iri,
+    predicate: Predicates,
     object: Iri
 }
 
 pub(crate) struct DdkgWriter {
     folder: PathBuf,
-    nodes: BTreeMap<Iri, NodeProps>,
-    edges: BTreeMap<Edge, String>,
+    evidence_selection: EvidenceSelection,
+    with_provenance: bool,
+    predicate_style: PredicateStyle,
+    merge_policy: MergePolicy,
+    nodes: NodeLabels,
+    edges: BTreeMap<Edge, Vec<Evidence>>,
+    sources: Vec<SourceDef>,
+    source_objects: Vec<(String, usize, ObjectInfo)>,
 }
 
 impl DdkgWriter {
-    pub(crate) fn new(folder: PathBuf) -> DdkgWriter {
+    pub(crate) fn new(folder: PathBuf, options: &OutputOptions) -> DdkgWriter {
         DdkgWriter {
             folder,
-            nodes: BTreeMap::new(),
+            evidence_selection: options.evidence.clone(),
+            with_provenance: options.provenance,
+            predicate_style: options.predicates,
+            merge_policy: options.merge,
+            nodes: NodeLabels::new(),
             edges: BTreeMap::new(),
+            sources: Vec::new(),
+            source_objects: Vec::new(),
+        }
+    }
+}
+
+impl DdkgWriter {
+    fn write_edges(&self, path: &Path, columns: &[String],
+                   node_iris_to_ids: &BTreeMap<Iri, String>, unmapped: &mut BTreeSet<Iri>)
+        -> Result<(), Error> {
+        let mut writer = BufWriter::new(io::create_file(path)?);
+        write!(writer, "subject_id\trelationship\tobject_id\tevidence_class\tconfidence")?;
+        for column in columns {
+            write!(writer, "\t{}", column)?;
+        }
+        if self.with_provenance {
+            write!(writer, "\tsource\trecords")?;
+        }
+        writeln!(writer)?;
+        for (triple, evidences) in &self.edges {
+            let subject_id = node_iris_to_ids.get(&triple.subject);
+            let object_id = node_iris_to_ids.get(&triple.object);
+            let (subject_id, object_id) = match (subject_id, object_id) {
+                (None, _) => {
+                    unmapped.insert(triple.subject.clone());
+                    continue;
+                }
+                (_, None) => {
+                    unmapped.insert(triple.object.clone());
+                    continue;
+                }
+                (Some(subject_id), Some(object_id)) => (subject_id, object_id),
+            };
+            let relationship = self.predicate_style.format(triple.predicate);
+            for group in self.merge_policy.group(evidences) {
+                let evidence_class =
+                    join_values(&group, |evidence| escape_tsv(&evidence.to_string()));
+                let confidence = merge::group_confidence(&group)
+                    .map(|confidence| confidence.to_string()).unwrap_or_default();
+                write!(writer, "{}\t{}\t{}\t{}\t{}", subject_id, relationship, object_id,
+                       evidence_class, confidence)?;
+                for column in columns {
+                    let values = join_values(&group, |evidence| {
+                        evidence.get(column).map(|value| escape_tsv(&value.to_string()))
+                            .unwrap_or_default()
+                    });
+                    write!(writer, "\t{}", values)?;
+                }
+                if self.with_provenance {
+                    let sources =
+                        join_values(&group, |evidence| escape_tsv(evidence.source()));
+                    let records = join_values(&group, |evidence| {
+                        let records: Vec<String> =
+                            evidence.records().iter().map(|record| record.to_string())
+                                .collect();
+                        records.join(",")
+                    });
+                    write!(writer, "\t{}\t{}", sources, records)?;
+                }
+                writeln!(writer)?;
+            }
         }
+        Ok(())
     }
 }
 
 impl GraphWriter for DdkgWriter {
     fn add_node(&mut self, node: &Iri, _class: &Iri, label: &str) {
-        self.nodes.insert(
-            node.clone(),
-            NodeProps {
-                label: label.to_string(),
-            },
-        );
+        self.nodes.add(node, label, LabelOrigin::Data);
+    }
+
+    fn add_ontology_label(&mut self, node: &Iri, label: &str) {
+        self.nodes.add(node, label, LabelOrigin::Ontology);
     }
 
-    fn add_edge(&mut self, subject: &Iri, predicate: &Iri, object: &Iri, evidence_class: &str) {
+    fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                evidence: &Evidence) {
         let edge = Edge {
             subject: subject.clone(),
-            predicate: predicate.clone(),
+            predicate,
             object: object.clone(),
         };
-        self.edges.insert(edge, evidence_class.to_string());
+        self.edges.entry(edge).or_default().push(evidence.clone());
+    }
+
+    fn add_source(&mut self, source: &SourceDef, objects: &[ObjectInfo]) {
+        self.sources.push(source.clone());
+        for (index, object) in objects.iter().enumerate() {
+            self.source_objects.push((source.name.clone(), index + 1, object.clone()));
+        }
+    }
+
+    fn label_conflicts(&self) -> LabelConflicts {
+        self.nodes.conflicts()
     }
 
-    fn serialize(&self) -> Result<(), Error> {
+    fn serialize(&mut self) -> Result<(), Error> {
         let iris_to_ids = create_node_ids(&self.nodes)?;
         let nodes_file = self.folder.join(NODES_FILE);
         let mut unmapped: BTreeSet<Iri> = BTreeSet::new();
         write_nodes(&nodes_file, &self.nodes, &iris_to_ids, &mut unmapped)?;
         let edges_file = self.folder.join(EDGES_FILE);
-        write_edges(&edges_file, &self.edges, &iris_to_ids, &mut unmapped)?;
+        let columns = self.evidence_selection.columns(self.edges.values().flatten());
+        self.write_edges(&edges_file, &columns, &iris_to_ids, &mut unmapped)?;
         let unmapped_file = self.folder.join(UNMAPPED_FILE);
         write_unmapped(&unmapped_file, &unmapped)?;
+        let sources_file = self.folder.join(SOURCES_FILE);
+        write_sources(&sources_file, &self.sources)?;
+        if self.with_provenance {
+            let source_objects_file = self.folder.join(SOURCE_OBJECTS_FILE);
+            write_source_objects(&source_objects_file, &self.source_objects)?;
+        }
         Ok(())
     }
 }
 
-fn create_node_ids(nodes: &BTreeMap<Iri, NodeProps>)
+fn create_node_ids(nodes: &NodeLabels)
     -> Result<BTreeMap<Iri, String>, Error> {
     let mut node_ids: BTreeMap<Iri, String> = BTreeMap::new();
-    for iri in nodes.keys() {
+    for iri in nodes.nodes() {
         match node_iri_to_id(iri) {
             Some(id) => {
                 node_ids.insert(iri.clone(), id);
@@ -88,7 +184,7 @@ fn create_node_ids(nodes: &BTreeMap<Iri, NodeProps>)
     Ok(node_ids)
 }
 
-fn node_iri_to_id(iri: &Iri) -> Option<String> {
+pub(crate) fn node_iri_to_id(iri: &Iri) -> Option<String> {
     if let Some(hgnc) = iri.strip_prefix(penyu::vocabs::hgnc::NAMESPACE) {
         Some(format!("HGNC:{}", hgnc))
     } else if let Some(mondo) = iri.strip_prefix(penyu::vocabs::obo::ns::MONDO) {
@@ -112,57 +208,63 @@ fn node_iri_to_id(iri: &Iri) -> Option<String> {
 
 fn write_nodes(
     path: &Path,
-    nodes: &BTreeMap<Iri, NodeProps>,
+    nodes: &NodeLabels,
     node_iris_to_ids: &BTreeMap<Iri, String>,
     unmapped: &mut BTreeSet<Iri>
 ) -> Result<(), Error> {
     let mut writer = BufWriter::new(io::create_file(path)?);
-    writeln!(writer, "node_id\tnode_label")?;
-    for (iri, props) in nodes {
+    writeln!(writer, "node_id\tnode_label\tnode_synonyms")?;
+    for (iri, label, synonyms) in nodes.iter() {
         let id = node_iris_to_ids.get(iri);
         match id {
-            Some(id) => writeln!(writer, "{}\t{}", id, props.label)?,
+            Some(id) => {
+                let synonyms: Vec<String> =
+                    synonyms.iter().map(|synonym| escape_tsv(synonym)).collect();
+                writeln!(writer, "{}\t{}\t{}", id, escape_tsv(label), synonyms.join("|"))?
+            }
             None => { unmapped.insert(iri.clone()); }
         }
     }
     Ok(())
 }
 
-fn write_edges(
-    path: &Path,
-    edges: &BTreeMap<Edge, String>,
-    node_iris_to_ids: &BTreeMap<Iri, String>,
-    unmapped: &mut BTreeSet<Iri>
+fn write_unmapped(path: &Path, unmapped: &BTreeSet<Iri>) -> Result<(), Error> {
+    let mut writer = BufWriter::new(io::create_file(path)?);
+    for iri in unmapped {
+        writeln!(writer, "{}", iri)?;
+    }
+    Ok(())
+}
 
-) -> Result<(), Error> {
+fn write_sources(path: &Path, sources: &[SourceDef]) -> Result<(), Error> {
     let mut writer = BufWriter::new(io::create_file(path)?);
-    writeln!(writer, "subject_id\trelationship\tobject_id\tevidence_class")?;
-    for (triple, evidence_class) in edges {
-        let subject_id = node_iris_to_ids.get(&triple.subject);
-        match subject_id {
-            None => { unmapped.insert(triple.subject.clone()); },
-            Some(subject_id) => {
-                let object_id = node_iris_to_ids.get(&triple.object);
-                match object_id {
-                    None => { unmapped.insert(triple.object.clone()); },
-                    Some(object_id) => {
-                        writeln!(
-                            writer,
-                            "{}\t{}\t{}\t{}",
-                            subject_id, triple.predicate, object_id, evidence_class
-                        )?;
-                    }
-                }
-            }
-        }
+    writeln!(writer, "source\tlocation\trelease")?;
+    for source in sources {
+        writeln!(writer, "{}\t{}\t{}", escape_tsv(&source.name),
+                 escape_tsv(&source.location.to_string()),
+                 escape_tsv(source.release.as_deref().unwrap_or("")))?;
     }
     Ok(())
 }
 
-fn write_unmapped(path: &Path, unmapped: &BTreeSet<Iri>) -> Result<(), Error> {
+fn write_source_objects(path: &Path, source_objects: &[(String, usize, ObjectInfo)])
+    -> Result<(), Error> {
     let mut writer = BufWriter::new(io::create_file(path)?);
-    for iri in unmapped {
-        writeln!(writer, "{}", iri)?;
+    writeln!(writer, "source\tindex\tobject\tsize\te_tag\tversion\tlast_modified")?;
+    for (source, index, object) in source_objects {
+        writeln!(writer, "{}\t{}\t{}\t{}\t{}\t{}\t{}", escape_tsv(source), index,
+                 escape_tsv(&object.name), object.size,
+                 escape_tsv(object.e_tag.as_deref().unwrap_or("")),
+                 escape_tsv(object.version.as_deref().unwrap_or("")),
+                 escape_tsv(object.last_modified.as_deref().unwrap_or("")))?;
     }
     Ok(())
 }
+
+fn join_values<F>(group: &[&Evidence], value: F) -> String
+where
+    F: Fn(&Evidence) -> String,
+{
+    let values: Vec<String> = group.iter().map(|evidence| value(evidence)).collect();
+    values.join(";")
+}
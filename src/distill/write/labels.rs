@@ -0,0 +1,100 @@
+use penyu::model::iri::Iri;
+use std::collections::BTreeMap;
+
+const N_EXAMPLES: usize = 5;
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub(crate) enum LabelOrigin {
+    Ontology,
+    Data,
+}
+
+struct NodeLabel {
+    label: String,
+    origin: LabelOrigin,
+    count: usize,
+}
+
+pub(crate) struct NodeLabels {
+    labels: BTreeMap<Iri, Vec<NodeLabel>>,
+}
+
+/// Nodes with more than one label, with the first few as examples.
+pub(crate) struct LabelConflicts {
+    pub(crate) n_conflicts: usize,
+    pub(crate) n_nodes: usize,
+    pub(crate) examples: Vec<String>,
+}
+
+impl NodeLabels {
+    pub(crate) fn new() -> NodeLabels {
+        NodeLabels { labels: BTreeMap::new() }
+    }
+    pub(crate) fn add(&mut self, node: &Iri, label: &str, origin: LabelOrigin) {
+        let labels = self.labels.entry(node.clone()).or_default();
+        match labels.iter_mut().find(|node_label| node_label.label == label) {
+            Some(node_label) => {
+                node_label.count += 1;
+                if origin == LabelOrigin::Ontology {
+                    node_label.origin = origin;
+                }
+            }
+            None => labels.push(NodeLabel { label: label.to_string(), origin, count: 1 }),
+        }
+    }
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = &Iri> {
+        self.labels.keys()
+    }
+    /// The ontology label if there is one, otherwise the data label seen most often, with ties
+    /// going to the label seen first.
+    fn preferred_index(labels: &[NodeLabel]) -> usize {
+        let mut preferred: usize = 0;
+        for (index, node_label) in labels.iter().enumerate().skip(1) {
+            let current = &labels[preferred];
+            let is_better = match (node_label.origin, current.origin) {
+                (LabelOrigin::Ontology, LabelOrigin::Data) => true,
+                (LabelOrigin::Data, LabelOrigin::Ontology) => false,
+                _ => node_label.count > current.count,
+            };
+            if is_better {
+                preferred = index;
+            }
+        }
+        preferred
+    }
+    pub(crate) fn preferred_and_synonyms(&self, node: &Iri) -> Option<(&str, Vec<&str>)> {
+        let labels = self.labels.get(node)?;
+        let preferred = NodeLabels::preferred_index(labels);
+        let synonyms: Vec<&str> =
+            labels.iter().enumerate().filter(|(index, _)| *index != preferred)
+                .map(|(_, node_label)| node_label.label.as_str()).collect();
+        Some((labels[preferred].label.as_str(), synonyms))
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Iri, &str, Vec<&str>)> {
+        self.labels.keys().filter_map(|node| {
+            self.preferred_and_synonyms(node)
+                .map(|(preferred, synonyms)| (node, preferred, synonyms))
+        })
+    }
+    pub(crate) fn conflicts(&self) -> LabelConflicts {
+        let conflicts: Vec<(&Iri, &str, Vec<&str>)> =
+            self.iter().filter(|(_, _, synonyms)| !synonyms.is_empty()).collect();
+        let examples: Vec<String> =
+            conflicts.iter().take(N_EXAMPLES).map(|(node, preferred, synonyms)| {
+                format!("{}: '{}', also '{}'", node, preferred, synonyms.join("', '"))
+            }).collect();
+        LabelConflicts { n_conflicts: conflicts.len(), n_nodes: self.labels.len(), examples }
+    }
+}
+
+impl LabelConflicts {
+    pub(crate) fn new() -> LabelConflicts {
+        LabelConflicts { n_conflicts: 0, n_nodes: 0, examples: Vec::new() }
+    }
+    pub(crate) fn report(&self) {
+        eprintln!("{} of {} nodes have conflicting labels.", self.n_conflicts, self.n_nodes);
+        for example in &self.examples {
+            eprintln!("  {}", example);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::distill::evidence::Evidence;
+use crate::error::Error;
+use crate::vocabs::Predicates;
+use penyu::model::iri::Iri;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy)]
+pub(crate) enum MergePolicy {
+    All,
+    Best,
+    Combine,
+}
+
+mod policies {
+    pub(crate) const ALL: &str = "all";
+    pub(crate) const BEST: &str = "best";
+    pub(crate) const COMBINE: &str = "combine";
+    pub(crate) const KNOWN: [&str; 3] = [ALL, BEST, COMBINE];
+}
+
+impl TryFrom<&str> for MergePolicy {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            policies::ALL => Ok(MergePolicy::All),
+            policies::BEST => Ok(MergePolicy::Best),
+            policies::COMBINE => Ok(MergePolicy::Combine),
+            _ => Err(Error::from(format!(
+                "Unknown merge policy '{}'. Known merge policies are '{}'.", string,
+                policies::KNOWN.join("', '")
+            ))),
+        }
+    }
+}
+
+impl Display for MergePolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergePolicy::All => write!(f, "{}", policies::ALL),
+            MergePolicy::Best => write!(f, "{}", policies::BEST),
+            MergePolicy::Combine => write!(f, "{}", policies::COMBINE),
+        }
+    }
+}
+
+impl MergePolicy {
+    /// Groups the evidence of one triple into the edge records to write, one group per record.
+    pub(crate) fn group<'a>(&self, evidences: &'a [Evidence]) -> Vec<Vec<&'a Evidence>> {
+        match self {
+            MergePolicy::All => evidences.iter().map(|evidence| vec![evidence]).collect(),
+            MergePolicy::Best => best(evidences).map(|evidence| vec![vec![evidence]])
+                .unwrap_or_default(),
+            MergePolicy::Combine => vec![evidences.iter().collect()],
+        }
+    }
+}
+
+/// The confidence of an edge record, combining the confidence of its evidences by noisy-OR,
+/// i.e. the probability that at least one of them holds. Evidences without confidence are
+/// left out, and if none has confidence, neither has the edge record.
+pub(crate) fn group_confidence(group: &[&Evidence]) -> Option<f64> {
+    let confidences: Vec<f64> =
+        group.iter().filter_map(|evidence| evidence.confidence()).collect();
+    if confidences.is_empty() {
+        return None;
+    }
+    let none_holds: f64 = confidences.iter().map(|confidence| 1.0 - confidence).product();
+    Some(1.0 - none_holds)
+}
+
+/// Counts the edges supported by more than one source, which are the ones the merge policy
+/// applies to, and all edges.
+pub(crate) fn count_shared_edges<'a, I>(edge_sources: I) -> (usize, usize)
+where
+    I: Iterator<Item = (&'a Iri, Predicates, &'a Iri, &'a str)>,
+{
+    let mut sources_by_edge: BTreeMap<(&Iri, Predicates, &Iri), BTreeSet<&str>> =
+        BTreeMap::new();
+    for (subject, predicate, object, source) in edge_sources {
+        sources_by_edge.entry((subject, predicate, object)).or_default().insert(source);
+    }
+    let n_shared = sources_by_edge.values().filter(|sources| sources.len() > 1).count();
+    (n_shared, sources_by_edge.len())
+}
+
+fn best(evidences: &[Evidence]) -> Option<&Evidence> {
+    let mut best: Option<&Evidence> = None;
+    for evidence in evidences {
+        if best.is_none_or(|best| evidence.is_better_than(best)) {
+            best = Some(evidence);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence(confidence: Option<f64>) -> Evidence {
+        let mut evidence = Evidence::new("test");
+        if let Some(confidence) = confidence {
+            evidence.set_confidence(confidence);
+        }
+        evidence
+    }
+
+    #[test]
+    fn combine_takes_noisy_or_of_confidences() {
+        let evidences = [evidence(Some(0.5)), evidence(None), evidence(Some(0.5))];
+        let groups = MergePolicy::Combine.group(&evidences);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(group_confidence(&groups[0]), Some(0.75));
+    }
+
+    #[test]
+    fn single_evidence_keeps_its_confidence() {
+        let evidences = [evidence(Some(0.3)), evidence(Some(0.6))];
+        let groups = MergePolicy::Best.group(&evidences);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(group_confidence(&groups[0]), Some(0.6));
+        assert_eq!(group_confidence(&[&evidence(None)]), None);
+    }
+}
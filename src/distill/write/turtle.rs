@@ -1,32 +1,195 @@
 use penyu::model::graph::MemoryGraph;
 use penyu::model::iri::Iri;
-use penyu::model::literal::Literal;
+use penyu::model::literal::{Literal, LiteralTag};
 use penyu::vocabs::{obo, rdf, rdfs, uniprot, xsd};
-use crate::distill::write::GraphWriter;
+use crate::data::SourceDef;
+use crate::distill::evidence::{Evidence, EvidenceSelection, EvidenceValue};
+use crate::distill::write::labels::{LabelConflicts, LabelOrigin, NodeLabels};
+use crate::distill::write::merge::{self, MergePolicy};
+use crate::distill::write::{GraphWriter, PredicateStyle};
+use crate::distill::OutputOptions;
 use crate::error::Error;
+use crate::location::ObjectInfo;
 use crate::vocabs;
+use crate::vocabs::{prov, skos, Predicates};
+use std::collections::{BTreeMap, BTreeSet};
 
 pub(crate) struct TurtleWriter {
-    pub(crate) graph: MemoryGraph
+    pub(crate) graph: MemoryGraph,
+    evidence_selection: EvidenceSelection,
+    with_provenance: bool,
+    predicate_style: PredicateStyle,
+    merge_policy: MergePolicy,
+    node_labels: NodeLabels,
+    predicates: BTreeSet<Predicates>,
+    statements: BTreeMap<(Iri, Predicates, Iri), Vec<Evidence>>,
+    n_edges: usize,
 }
 
 impl TurtleWriter {
-    pub(crate) fn new() -> TurtleWriter {
+    pub(crate) fn new(options: &OutputOptions) -> TurtleWriter {
         let mut graph = MemoryGraph::new();
         add_prefixes(&mut graph);
-        TurtleWriter { graph }
+        TurtleWriter {
+            graph,
+            evidence_selection: options.evidence.clone(),
+            with_provenance: options.provenance,
+            predicate_style: options.predicates,
+            merge_policy: options.merge,
+            node_labels: NodeLabels::new(),
+            predicates: BTreeSet::new(),
+            statements: BTreeMap::new(),
+            n_edges: 0,
+        }
+    }
+    fn add_predicate(&mut self, predicate: Predicates) {
+        if !self.predicates.insert(predicate) {
+            return;
+        }
+        let def = predicate.def();
+        self.graph.add(predicate.iri(), rdfs::LABEL.clone(), Literal::from(def.label.to_string()));
+        self.graph.add(predicate.iri(), vocabs::props::BIOLINK_PREDICATE.clone(),
+                       vocabs::expand_biolink_curie(def.biolink));
+        self.graph.add(predicate.iri(), vocabs::props::BIOLINK_INVERSE.clone(),
+                       vocabs::expand_biolink_curie(def.inverse));
+    }
+    fn add_labels(&mut self) {
+        for (node, label, synonyms) in self.node_labels.iter() {
+            self.graph.add(node, rdfs::LABEL.clone(), Literal::from(label.to_string()));
+            for synonym in synonyms {
+                self.graph.add(node, skos::ALT_LABEL.clone(), Literal::from(synonym.to_string()));
+            }
+        }
+    }
+    fn add_statements(&mut self) {
+        let statements = std::mem::take(&mut self.statements);
+        for ((subject, predicate, object), evidences) in &statements {
+            for group in self.merge_policy.group(evidences) {
+                self.n_edges += 1;
+                let edge_iri = vocabs::create_edge_iri(self.n_edges);
+                self.graph.add(&edge_iri, rdf::TYPE.clone(), vocabs::reification::STATEMENT);
+                self.graph.add(&edge_iri, vocabs::reification::SUBJECT.clone(), subject);
+                self.graph.add(&edge_iri, vocabs::reification::PREDICATE.clone(),
+                               predicate.iri());
+                self.graph.add(&edge_iri, vocabs::reification::OBJECT.clone(), object);
+                if let [evidence] = group.as_slice() {
+                    self.add_evidence(&edge_iri, evidence);
+                } else {
+                    if let Some(confidence) = merge::group_confidence(&group) {
+                        self.graph.add(&edge_iri, vocabs::props::CONFIDENCE.clone(),
+                                       EvidenceValue::Number(confidence).to_literal());
+                    }
+                    for (index, evidence) in group.iter().enumerate() {
+                        let evidence_iri = edge_iri.join(format!("/{}", index + 1));
+                        self.graph.add(&edge_iri, vocabs::props::SUPPORTED_BY.clone(),
+                                       &evidence_iri);
+                        self.add_evidence(&evidence_iri, evidence);
+                    }
+                }
+            }
+        }
+    }
+    fn add_evidence(&mut self, edge_iri: &Iri, evidence: &Evidence) {
+        let source_iri = vocabs::create_source_iri(evidence.source());
+        self.graph.add(edge_iri, vocabs::props::SOURCE.clone(), &source_iri);
+        if self.with_provenance {
+            let objects: BTreeSet<usize> =
+                evidence.records().iter().map(|record| record.object).collect();
+            if objects.is_empty() {
+                self.graph.add(edge_iri, prov::WAS_DERIVED_FROM.clone(), source_iri);
+            }
+            for object in objects {
+                let object_iri = vocabs::create_object_iri(evidence.source(), object);
+                self.graph.add(edge_iri, prov::WAS_DERIVED_FROM.clone(), object_iri);
+            }
+            for record in evidence.records() {
+                self.graph.add(edge_iri, vocabs::props::RECORD.clone(),
+                               Literal::from(record.to_string()));
+            }
+        }
+        if let Some(confidence) = evidence.confidence() {
+            self.graph.add(edge_iri, vocabs::props::CONFIDENCE.clone(),
+                           EvidenceValue::Number(confidence).to_literal());
+        }
+        if self.evidence_selection.is_none() {
+            return;
+        }
+        for (name, value) in evidence.properties() {
+            if self.evidence_selection.selects(name) {
+                self.graph.add(edge_iri, vocabs::create_evidence_iri(name), value.to_literal());
+            }
+        }
+        if let Some((name, direction)) = evidence.score() {
+            self.graph.add(edge_iri, vocabs::props::SCORE.clone(),
+                           vocabs::create_evidence_iri(name));
+            self.graph.add(edge_iri, vocabs::props::SCORE_DIRECTION.clone(),
+                           Literal::from(direction.to_string()));
+        }
     }
 }
 impl GraphWriter for TurtleWriter {
     fn add_node(&mut self, node: &Iri, class: &Iri, label: &str) {
         self.graph.add(node.clone(), rdf::TYPE.clone(), class);
-        self.graph.add(node, rdfs::LABEL.clone(), Literal::from(label.to_string()));
+        self.node_labels.add(node, label, LabelOrigin::Data);
+    }
+    fn add_ontology_label(&mut self, node: &Iri, label: &str) {
+        self.node_labels.add(node, label, LabelOrigin::Ontology);
+    }
+    fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                evidence: &Evidence) {
+        if matches!(self.predicate_style, PredicateStyle::Label) {
+            self.add_predicate(predicate);
+        }
+        self.graph.add(subject, predicate.iri(), object);
+        if !self.evidence_selection.is_none() || self.with_provenance
+            || evidence.confidence().is_some() {
+            let key = (subject.clone(), predicate, object.clone());
+            self.statements.entry(key).or_default().push(evidence.clone());
+        }
+    }
+    fn add_source(&mut self, source: &SourceDef, objects: &[ObjectInfo]) {
+        let source_iri = vocabs::create_source_iri(&source.name);
+        self.graph.add(&source_iri, rdfs::LABEL.clone(), Literal::from(source.name.clone()));
+        self.graph.add(&source_iri, vocabs::props::LOCATION.clone(),
+                       Literal::from(source.location.to_string()));
+        if let Some(release) = &source.release {
+            self.graph.add(&source_iri, vocabs::props::RELEASE.clone(),
+                           Literal::from(release.clone()));
+        }
+        if !self.with_provenance {
+            return;
+        }
+        self.graph.add(&source_iri, rdf::TYPE.clone(), prov::COLLECTION);
+        for (index, object) in objects.iter().enumerate() {
+            let object_iri = vocabs::create_object_iri(&source.name, index + 1);
+            self.graph.add(&source_iri, prov::HAD_MEMBER.clone(), &object_iri);
+            self.graph.add(&object_iri, rdf::TYPE.clone(), prov::ENTITY);
+            self.graph.add(&object_iri, vocabs::props::LOCATION.clone(),
+                           Literal::from(object.name.clone()));
+            self.graph.add(&object_iri, vocabs::props::SIZE.clone(),
+                           Literal::new(object.size.to_string(),
+                                        LiteralTag::Type(xsd::INTEGER.clone())));
+            if let Some(e_tag) = &object.e_tag {
+                self.graph.add(&object_iri, vocabs::props::E_TAG.clone(),
+                               Literal::from(e_tag.clone()));
+            }
+            if let Some(version) = &object.version {
+                self.graph.add(&object_iri, vocabs::props::VERSION.clone(),
+                               Literal::from(version.clone()));
+            }
+            if let Some(last_modified) = &object.last_modified {
+                let date_time = xsd::NAMESPACE.join_str("dateTime");
+                self.graph.add(&object_iri, prov::GENERATED_AT_TIME.clone(),
+                               Literal::new(last_modified.clone(), LiteralTag::Type(date_time)));
+            }
+        }
     }
-    fn add_edge(&mut self, subject: &Iri, predicate: &Iri, object: &Iri,
-                           _evidence_class: &str) {
-        self.graph.add(subject, predicate, object);
+    fn label_conflicts(&self) -> LabelConflicts {
+        self.node_labels.conflicts()
     }
-    fn serialize(&self) -> Result<(), Error> {
+    fn serialize(&mut self) -> Result<(), Error> {
+        self.add_statements();
+        self.add_labels();
         penyu::write::turtle::write(&mut std::io::stdout(), &self.graph)?;
         Ok(())
     }
@@ -46,6 +209,13 @@ pub fn add_prefixes(graph: &mut MemoryGraph) {
     add_prefix(graph, vocabs::prefixes::DISEASE, vocabs::ns::DISEASE);
     add_prefix(graph, vocabs::prefixes::VARIANT, vocabs::ns::VARIANT);
     add_prefix(graph, vocabs::prefixes::PROTEIN, vocabs::ns::PROTEIN);
+    add_prefix(graph, vocabs::prefixes::SOURCE, vocabs::ns::SOURCE);
+    add_prefix(graph, vocabs::prefixes::EDGE, vocabs::ns::EDGE);
+    add_prefix(graph, vocabs::prefixes::EVIDENCE, vocabs::ns::EVIDENCE);
+    add_prefix(graph, vocabs::prefixes::PROV, prov::NAMESPACE);
+    add_prefix(graph, vocabs::prefixes::BIOLINK, vocabs::ns::BIOLINK);
+    add_prefix(graph, vocabs::prefixes::SKOS, skos::NAMESPACE);
+    add_prefix(graph, vocabs::prefixes::KP4CD, vocabs::NAMESPACE);
 }
 
 fn add_prefix(graph: &mut MemoryGraph, prefix: &str, namespace: &Iri) {
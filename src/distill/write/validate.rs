@@ -0,0 +1,174 @@
+use crate::data::SourceDef;
+use crate::distill::evidence::Evidence;
+use crate::distill::write::labels::LabelConflicts;
+use crate::distill::write::GraphWriter;
+use crate::error::Error;
+use crate::location::ObjectInfo;
+use crate::vocabs::{Concepts, Predicates};
+use penyu::model::iri::Iri;
+use std::collections::{BTreeMap, BTreeSet};
+
+const N_EXAMPLES: usize = 3;
+
+pub(crate) struct ValidatingWriter<'a, W: GraphWriter> {
+    inner: &'a mut W,
+    node_classes: BTreeMap<Iri, BTreeSet<Iri>>,
+    edges: Vec<(Iri, Predicates, Iri, String)>,
+}
+
+struct Violations {
+    counts: BTreeMap<String, usize>,
+    examples: BTreeMap<String, Vec<String>>,
+}
+
+impl<'a, W: GraphWriter> ValidatingWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> ValidatingWriter<'a, W> {
+        ValidatingWriter { inner, node_classes: BTreeMap::new(), edges: Vec::new() }
+    }
+    /// Reports violations on stderr and returns them as warnings, or in the error if strict.
+    pub(crate) fn validate(&self, strict: bool) -> Result<Vec<String>, Error> {
+        let mut violations = Violations::new();
+        let mut n_violations: usize = 0;
+        for (subject, predicate, object, source) in &self.edges {
+            let mut is_violation = false;
+            let def = predicate.def();
+            let example = format!("{} {} {}", subject, def.curie, object);
+            for (role, node, allowed) in
+                [("subject", subject, def.domain), ("object", object, def.range)] {
+                match self.node_classes.get(node) {
+                    None => {
+                        let problem =
+                            format!("{}: {} of '{}' was never declared as a node", source, role,
+                                    def.label);
+                        violations.add(problem, &example);
+                        is_violation = true;
+                    }
+                    Some(classes) => {
+                        if !allowed.is_empty() && !classes.iter().any(|class| {
+                            allowed.iter().any(|concept| concept.concept_iri() == class)
+                        }) {
+                            let problem = format!(
+                                "{}: {} of '{}' needs to be {}, but is {}", source, role,
+                                def.label, describe_allowed(allowed), describe_classes(classes)
+                            );
+                            violations.add(problem, &example);
+                            is_violation = true;
+                        }
+                    }
+                }
+            }
+            if is_violation {
+                n_violations += 1;
+            }
+        }
+        violations.report();
+        if n_violations == 0 {
+            Ok(Vec::new())
+        } else if strict {
+            Err(Error::from(format!(
+                "{} of {} edges violate predicate domain or range rules:\n{}", n_violations,
+                self.edges.len(), violations.warnings().join("\n")
+            )))
+        } else {
+            eprintln!("{} of {} edges violate predicate domain or range rules.", n_violations,
+                      self.edges.len());
+            Ok(violations.warnings())
+        }
+    }
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = &Iri> {
+        self.node_classes.keys()
+    }
+    pub(crate) fn node_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for class in self.node_classes.values().flatten() {
+            *counts.entry(class_name(class)).or_insert(0) += 1;
+        }
+        counts
+    }
+    pub(crate) fn edge_counts(&self) -> BTreeMap<String, usize> {
+        let edges: BTreeSet<(&Iri, Predicates, &Iri)> =
+            self.edges.iter().map(|(subject, predicate, object, _)| (subject, *predicate, object))
+                .collect();
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for (_, predicate, _) in edges {
+            *counts.entry(predicate.def().label.to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+    pub(crate) fn edge_sources(&self) -> impl Iterator<Item = (&Iri, Predicates, &Iri, &str)> {
+        self.edges.iter().map(|(subject, predicate, object, source)| {
+            (subject, *predicate, object, source.as_str())
+        })
+    }
+}
+
+impl<W: GraphWriter> GraphWriter for ValidatingWriter<'_, W> {
+    fn add_node(&mut self, node: &Iri, class: &Iri, label: &str) {
+        self.node_classes.entry(node.clone()).or_default().insert(class.clone());
+        self.inner.add_node(node, class, label);
+    }
+    fn add_ontology_label(&mut self, node: &Iri, label: &str) {
+        self.inner.add_ontology_label(node, label);
+    }
+    fn add_edge(&mut self, subject: &Iri, predicate: Predicates, object: &Iri,
+                evidence: &Evidence) {
+        self.edges.push(
+            (subject.clone(), predicate, object.clone(), evidence.source().to_string())
+        );
+        self.inner.add_edge(subject, predicate, object, evidence);
+    }
+    fn add_source(&mut self, source: &SourceDef, objects: &[ObjectInfo]) {
+        self.inner.add_source(source, objects);
+    }
+    fn label_conflicts(&self) -> LabelConflicts {
+        self.inner.label_conflicts()
+    }
+    fn produces_output(&self) -> bool {
+        self.inner.produces_output()
+    }
+    fn serialize(&mut self) -> Result<(), Error> {
+        self.inner.serialize()
+    }
+}
+
+impl Violations {
+    fn new() -> Violations {
+        Violations { counts: BTreeMap::new(), examples: BTreeMap::new() }
+    }
+    fn add(&mut self, problem: String, example: &str) {
+        let examples = self.examples.entry(problem.clone()).or_default();
+        if examples.len() < N_EXAMPLES {
+            examples.push(example.to_string());
+        }
+        *self.counts.entry(problem).or_insert(0) += 1;
+    }
+    fn report(&self) {
+        for (problem, count) in &self.counts {
+            eprintln!("{} ({} edges)", problem, count);
+            for example in &self.examples[problem] {
+                eprintln!("  e.g. {}", example);
+            }
+        }
+    }
+    fn warnings(&self) -> Vec<String> {
+        self.counts.iter().map(|(problem, count)| format!("{} ({} edges)", problem, count))
+            .collect()
+    }
+}
+
+fn describe_allowed(allowed: &[Concepts]) -> String {
+    let names: Vec<&str> = allowed.iter().map(|concept| concept.name()).collect();
+    names.join(" or ")
+}
+
+fn describe_classes(classes: &BTreeSet<Iri>) -> String {
+    let names: Vec<String> = classes.iter().map(class_name).collect();
+    names.join(" and ")
+}
+
+pub(crate) fn class_name(class: &Iri) -> String {
+    match Concepts::from_concept_iri(class) {
+        Some(concept) => concept.name().to_string(),
+        None => class.to_string(),
+    }
+}
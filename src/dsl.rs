@@ -1,6 +1,16 @@
-use crate::data::{get_data_location, Selection, Source};
+use crate::aggregate::{AggregateOptions, SortSpec};
+use crate::data;
+use crate::data::{get_data_location, Registry, Selection};
+use crate::distill::evidence::EvidenceSelection;
+use crate::distill::write::merge::MergePolicy;
+use crate::distill::write::PredicateStyle;
+use crate::distill::OutputOptions;
 use crate::error::Error;
-use crate::s3::S3Uri;
+use crate::filter::Filter;
+use crate::join::{JoinHow, JoinOptions};
+use crate::location::DataLocation;
+use crate::tabular::{TabularFormat, TabularOptions};
+use crate::verify::VerifyOptions;
 use std::path::PathBuf;
 
 mod commands {
@@ -12,7 +22,10 @@ mod commands {
     pub(crate) const REPORT_STATS: &str = "report-stats";
     pub(crate) const PRINT_TURTLE: &str = "print-turtle";
     pub(crate) const EXPORT_DDKG: &str = "export-ddkg";
-    pub(crate) const ALL: [&str; 8] = [
+    pub(crate) const AGGREGATE: &str = "aggregate";
+    pub(crate) const JOIN: &str = "join";
+    pub(crate) const VERIFY_SOURCES: &str = "verify-sources";
+    pub(crate) const ALL: [&str; 11] = [
         LIST_BUCKETS,
         PRINT_LINES,
         PRINT_SCHEMA,
@@ -21,34 +34,50 @@ mod commands {
         REPORT_STATS,
         PRINT_TURTLE,
         EXPORT_DDKG,
+        AGGREGATE,
+        JOIN,
+        VERIFY_SOURCES,
     ];
 }
 
-mod selections {
-    use crate::data;
-
-    pub(crate) const ALL: &str = "all";
-    pub(crate) const THREE: &str = "three";
-    pub(crate) const NOVARS: &str = "novars";
-    pub(crate) fn selections_help() -> String {
-        format!("Provide comma-separated list from '{}', '{}', '{}', and '{}'",
-                data::ALL_SOURCES.iter()
-                    .map(|source| source.to_string()).collect::<Vec<String>>()
-                    .join("', '") ,ALL, THREE, NOVARS)
-    }
+mod options {
+    pub(crate) const FORMAT: &str = "--format";
+    pub(crate) const OUTPUT: &str = "--output";
+    pub(crate) const WHERE: &str = "--where";
+    pub(crate) const BY: &str = "--by";
+    pub(crate) const SORT: &str = "--sort";
+    pub(crate) const ON: &str = "--on";
+    pub(crate) const HOW: &str = "--how";
+    pub(crate) const MEMORY_LIMIT: &str = "--memory-limit";
+    pub(crate) const RELEASE: &str = "--release";
+    pub(crate) const LINES: &str = "--lines";
+    pub(crate) const EVIDENCE: &str = "--evidence";
+    pub(crate) const PROVENANCE: &str = "--provenance";
+    pub(crate) const PREDICATES: &str = "--predicates";
+    pub(crate) const STRICT: &str = "--strict";
+    pub(crate) const MERGE: &str = "--merge";
+    pub(crate) const DRY_RUN: &str = "--dry-run";
+    pub(crate) const REPORT: &str = "--report";
+    pub(crate) const MIN_MAPPED: &str = "--min-mapped";
+    pub(crate) const MAX_UNMAPPED: &str = "--max-unmapped";
 }
+
 pub(crate) enum Command {
     ListBuckets,
-    PrintLines(S3Uri),
-    PrintSchema(S3Uri),
-    PrintTabular(S3Uri, Vec<String>),
-    ListSources,
+    PrintLines(DataLocation, Option<Filter>),
+    PrintSchema(DataLocation),
+    PrintTabular(DataLocation, TabularOptions),
+    ListSources(Option<String>),
     ReportStats(Selection),
-    PrintTurtle(Selection),
-    ExportDdkg(PathBuf, Selection),
+    PrintTurtle(Selection, OutputOptions),
+    ExportDdkg(PathBuf, Selection, OutputOptions),
+    Aggregate(DataLocation, AggregateOptions),
+    Join(DataLocation, DataLocation, JoinOptions),
+    VerifySources(VerifyOptions),
 }
 
-pub(crate) fn get_command_from_parts<I>(mut parts: I) -> Result<Command, Error>
+pub(crate) fn get_command_from_parts<I>(mut parts: I, registry: &Registry)
+    -> Result<Command, Error>
 where
     I: Iterator<Item = String>,
 {
@@ -56,31 +85,50 @@ where
         Some(arg) => match arg.as_str() {
             commands::LIST_BUCKETS => Ok(Command::ListBuckets),
             commands::PRINT_LINES => {
-                let s3uri = parse_object_argument(parts.next())?;
-                Ok(Command::PrintLines(s3uri))
+                let s3uri = parse_object_argument(parts.next(), registry)?;
+                let filter = parse_filter_option(parts)?;
+                Ok(Command::PrintLines(s3uri, filter))
             }
             commands::PRINT_SCHEMA => {
-                let s3uri = parse_object_argument(parts.next())?;
+                let s3uri = parse_object_argument(parts.next(), registry)?;
                 Ok(Command::PrintSchema(s3uri))
             }
             commands::PRINT_TABULAR => {
-                let s3uri = parse_object_argument(parts.next())?;
-                let columns = parts.collect();
-                Ok(Command::PrintTabular(s3uri, columns))
+                let s3uri = parse_object_argument(parts.next(), registry)?;
+                let options = parse_tabular_options(parts)?;
+                Ok(Command::PrintTabular(s3uri, options))
+            }
+            commands::LIST_SOURCES => {
+                let release = parse_release_option(parts)?;
+                Ok(Command::ListSources(release))
             }
-            commands::LIST_SOURCES => Ok(Command::ListSources),
             commands::REPORT_STATS => {
-                let selection = parse_selection_argument(parts.next())?;
+                let selection = parse_selection(parts, registry)?;
                 Ok(Command::ReportStats(selection))
             }
             commands::PRINT_TURTLE => {
-                let selection = parse_selection_argument(parts.next())?;
-                Ok(Command::PrintTurtle(selection))
+                let (selection, options) = parse_output_selection(parts, registry)?;
+                Ok(Command::PrintTurtle(selection, options))
             }
             commands::EXPORT_DDKG => {
                 let path = parse_path(parts.next())?;
-                let selection = parse_selection_argument(parts.next())?;
-                Ok(Command::ExportDdkg(path, selection))
+                let (selection, options) = parse_output_selection(parts, registry)?;
+                Ok(Command::ExportDdkg(path, selection, options))
+            }
+            commands::AGGREGATE => {
+                let s3uri = parse_object_argument(parts.next(), registry)?;
+                let options = parse_aggregate_options(parts)?;
+                Ok(Command::Aggregate(s3uri, options))
+            }
+            commands::JOIN => {
+                let left = parse_object_argument(parts.next(), registry)?;
+                let right = parse_object_argument(parts.next(), registry)?;
+                let options = parse_join_options(parts)?;
+                Ok(Command::Join(left, right, options))
+            }
+            commands::VERIFY_SOURCES => {
+                let options = parse_verify_options(parts)?;
+                Ok(Command::VerifySources(options))
             }
             _ => Err(Error::from(format!(
                 "Unknown command '{}'. {}",
@@ -95,45 +143,277 @@ where
     }
 }
 
-fn parse_object_argument(arg: Option<String>) -> Result<S3Uri, Error> {
+fn parse_object_argument(arg: Option<String>, registry: &Registry)
+    -> Result<DataLocation, Error> {
     match arg {
         Some(name) => {
-            let location = get_data_location(name.as_str())?;
+            let location = get_data_location(registry, name.as_str())?;
             Ok(location)
         }
         None => Err(Error::from("No object name provided.")),
     }
 }
 
-fn parse_selection_argument(arg: Option<String>) -> Result<Selection, Error> {
+fn parse_selection<I>(mut parts: I, registry: &Registry) -> Result<Selection, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut selection = parse_selection_argument(parts.next(), registry)?;
+    let release = parse_release_option(parts)?;
+    selection.resolve_releases(release.as_deref())?;
+    Ok(selection)
+}
+
+fn parse_output_selection<I>(mut parts: I, registry: &Registry)
+    -> Result<(Selection, OutputOptions), Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut selection = parse_selection_argument(parts.next(), registry)?;
+    let mut release: Option<String> = None;
+    let mut output_options = OutputOptions::new();
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::RELEASE => {
+                release = Some(parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a release.", options::RELEASE))
+                })?);
+            }
+            options::EVIDENCE => {
+                let names = parts.next().ok_or_else(|| {
+                    Error::from(format!(
+                        "Option {} needs comma-separated evidence properties or 'all'.",
+                        options::EVIDENCE
+                    ))
+                })?;
+                output_options.evidence = EvidenceSelection::parse(&names);
+            }
+            options::PROVENANCE => output_options.provenance = true,
+            options::STRICT => output_options.strict = true,
+            options::DRY_RUN => output_options.dry_run = true,
+            options::REPORT => {
+                let path = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a path.", options::REPORT))
+                })?;
+                output_options.report = Some(PathBuf::from(path));
+            }
+            options::MIN_MAPPED => {
+                let rates = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs comma-separated <tracker>=<rate>.",
+                                        options::MIN_MAPPED))
+                })?;
+                output_options.gates.parse_min_mapped(&rates)?;
+            }
+            options::MAX_UNMAPPED => {
+                let counts = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs comma-separated <tracker>=<count>.",
+                                        options::MAX_UNMAPPED))
+                })?;
+                output_options.gates.parse_max_unmapped(&counts)?;
+            }
+            options::MERGE => {
+                let policy = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a merge policy.", options::MERGE))
+                })?;
+                output_options.merge = MergePolicy::try_from(policy.as_str())?;
+            }
+            options::PREDICATES => {
+                let style = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a predicate style.",
+                                        options::PREDICATES))
+                })?;
+                output_options.predicates = PredicateStyle::try_from(style.as_str())?;
+            }
+            _ => {
+                return Err(Error::from(format!(
+                    "Unexpected argument '{}'. Only options {}, {}, {}, {}, {}, {}, {}, {}, {} \
+                    and {} are supported here.", part, options::RELEASE, options::EVIDENCE,
+                    options::PROVENANCE, options::PREDICATES, options::STRICT, options::MERGE,
+                    options::DRY_RUN, options::REPORT, options::MIN_MAPPED, options::MAX_UNMAPPED
+                )));
+            }
+        }
+    }
+    selection.resolve_releases(release.as_deref())?;
+    Ok((selection, output_options))
+}
+
+fn parse_selection_argument(arg: Option<String>, registry: &Registry)
+    -> Result<Selection, Error> {
     match arg {
-        Some(arg) => {
-            let mut selection = Selection::new();
-            for part in arg.split(',') {
-                if part == selections::ALL {
-                    selection.all_sources();
-                } else if part == selections::THREE {
-                    selection.three_sources();
-                } else if part == selections::NOVARS {
-                    selection.no_variants();
-                } else if let Ok(source) = Source::try_from(part) {
-                    selection.add_source(source);
-                } else {
-                    return Err(Error::from(format!(
-                        "Unknown selection '{}'. {}.", part, selections::selections_help()
-                    )));
-                }
-            }
-            Ok(selection)
-        },
+        Some(arg) => Selection::parse(&arg, registry),
         None => {
             Err(Error::from(
-                format!("No selection provided. {}", selections::selections_help())
+                format!("No selection provided. {}", data::selections_help(registry))
             ))
         },
     }
 }
 
+fn parse_tabular_options<I>(mut parts: I) -> Result<TabularOptions, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut tabular_options = TabularOptions::new();
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::FORMAT => {
+                let format = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a value.", options::FORMAT))
+                })?;
+                tabular_options.format = TabularFormat::try_from(format.as_str())?;
+            }
+            options::OUTPUT => {
+                tabular_options.output = Some(parse_path(parts.next())?);
+            }
+            options::WHERE => {
+                tabular_options.filter = Some(parse_filter(parts.next())?);
+            }
+            _ => tabular_options.columns.push(part),
+        }
+    }
+    Ok(tabular_options)
+}
+
+fn parse_aggregate_options<I>(mut parts: I) -> Result<AggregateOptions, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut by: Vec<String> = Vec::new();
+    let mut sort: Option<SortSpec> = None;
+    let mut rest: Vec<String> = Vec::new();
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::BY => {
+                let fields = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs comma-separated fields.", options::BY))
+                })?;
+                by.extend(fields.split(',').map(|field| field.to_string()));
+            }
+            options::SORT => {
+                let column = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs an output column.", options::SORT))
+                })?;
+                sort = Some(SortSpec::parse(&column));
+            }
+            _ => rest.push(part),
+        }
+    }
+    let tabular_options = parse_tabular_options(rest.into_iter())?;
+    AggregateOptions::new(by, sort, tabular_options)
+}
+
+fn parse_join_options<I>(mut parts: I) -> Result<JoinOptions, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut join_options = JoinOptions::new();
+    let mut rest: Vec<String> = Vec::new();
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::ON => {
+                let keys = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs comma-separated keys.", options::ON))
+                })?;
+                join_options.add_keys(&keys);
+            }
+            options::HOW => {
+                let how = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a join type.", options::HOW))
+                })?;
+                join_options.how = JoinHow::try_from(how.as_str())?;
+            }
+            options::MEMORY_LIMIT => {
+                let limit = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a size in MB.", options::MEMORY_LIMIT))
+                })?;
+                join_options.memory_limit_mb = limit.parse::<u64>()?;
+            }
+            _ => rest.push(part),
+        }
+    }
+    join_options.tabular = parse_tabular_options(rest.into_iter())?;
+    Ok(join_options)
+}
+
+fn parse_verify_options<I>(mut parts: I) -> Result<VerifyOptions, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut verify_options = VerifyOptions::new();
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::LINES => {
+                let n_lines = parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a number of lines.", options::LINES))
+                })?;
+                verify_options.n_lines = n_lines.parse::<usize>()?;
+            }
+            options::RELEASE => {
+                verify_options.release = Some(parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a release.", options::RELEASE))
+                })?);
+            }
+            _ => {
+                return Err(Error::from(format!(
+                    "Unexpected argument '{}'. Only options {} and {} are supported here.",
+                    part, options::LINES, options::RELEASE
+                )));
+            }
+        }
+    }
+    Ok(verify_options)
+}
+
+fn parse_filter_option<I>(mut parts: I) -> Result<Option<Filter>, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut filter: Option<Filter> = None;
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::WHERE => filter = Some(parse_filter(parts.next())?),
+            _ => {
+                return Err(Error::from(format!(
+                    "Unexpected argument '{}'. Only option {} is supported here.",
+                    part, options::WHERE
+                )));
+            }
+        }
+    }
+    Ok(filter)
+}
+
+fn parse_release_option<I>(mut parts: I) -> Result<Option<String>, Error>
+where
+    I: Iterator<Item = String>,
+{
+    let mut release: Option<String> = None;
+    while let Some(part) = parts.next() {
+        match part.as_str() {
+            options::RELEASE => {
+                release = Some(parts.next().ok_or_else(|| {
+                    Error::from(format!("Option {} needs a release.", options::RELEASE))
+                })?);
+            }
+            _ => {
+                return Err(Error::from(format!(
+                    "Unexpected argument '{}'. Only option {} is supported here.",
+                    part, options::RELEASE
+                )));
+            }
+        }
+    }
+    Ok(release)
+}
+
+fn parse_filter(arg: Option<String>) -> Result<Filter, Error> {
+    match arg {
+        Some(expression) => Filter::parse(&expression),
+        None => Err(Error::from(format!("Option {} needs an expression.", options::WHERE))),
+    }
+}
+
 fn parse_path(arg: Option<String>) -> Result<PathBuf, Error> {
     match arg {
         Some(name) => Ok(PathBuf::from(name)),
@@ -1,24 +1,39 @@
-use crate::{buckets, data, distill, json, read};
+use crate::{aggregate, buckets, data, distill, join, json, read, verify};
+use crate::data::Registry;
 use crate::dsl::Command;
 use crate::error::Error;
 use crate::runtime::Runtime;
 
-pub(crate) fn execute(runtime: &Runtime, command: &Command) -> Result<(), Error> {
+pub(crate) fn execute(runtime: &Runtime, registry: &Registry, command: &Command)
+    -> Result<(), Error> {
     match command {
         Command::ListBuckets => buckets::list(runtime),
-        Command::PrintLines(s3uri) => read::print_lines(runtime, s3uri),
-        Command::PrintSchema(s3uri) => { json::print_schema(runtime, s3uri) }
-        Command::PrintTabular(s3uri, columns) => {
-            json::print_tabular(runtime, s3uri, columns)
+        Command::PrintLines(location, filter) => {
+            read::print_lines(runtime, location, filter.as_ref())
         }
-        Command::ListSources => {
-            data::list_sources();
+        Command::PrintSchema(location) => { json::print_schema(runtime, location) }
+        Command::PrintTabular(location, options) => {
+            json::print_tabular(runtime, location, options)
+        }
+        Command::ListSources(release) => {
+            data::list_sources(registry, release.as_deref());
             Ok(())
         }
         Command::ReportStats(selection) => { distill::report_stats(runtime, selection) }
-        Command::PrintTurtle(selection) => { distill::print_turtle(runtime, selection) }
-        Command::ExportDdkg(path, selection) => {
-            distill::export_ubkg(runtime, path, selection)
+        Command::PrintTurtle(selection, options) => {
+            distill::print_turtle(runtime, selection, options)
+        }
+        Command::ExportDdkg(path, selection, options) => {
+            distill::export_ubkg(runtime, path, selection, options)
+        }
+        Command::Aggregate(location, options) => {
+            aggregate::aggregate(runtime, location, options)
+        }
+        Command::Join(left, right, options) => {
+            join::join(runtime, left, right, options)
+        }
+        Command::VerifySources(options) => {
+            verify::verify_sources(runtime, registry, options)
         }
     }
 }
\ No newline at end of file
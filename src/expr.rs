@@ -0,0 +1,512 @@
+use crate::error::Error;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone)]
+pub(crate) struct FieldPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+pub(crate) enum ValueExpr {
+    Path(FieldPath),
+    Literal(Value),
+    Neg(Box<ValueExpr>),
+    Arith(Box<ValueExpr>, ArithOp, Box<ValueExpr>),
+    Call(Function, Vec<ValueExpr>),
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum Function {
+    Log10,
+    Ln,
+    Exp,
+    Abs,
+    Sqrt,
+    Round,
+    Concat,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) enum Token {
+    Ident(String),
+    Field(String),
+    Str(String),
+    Num(f64),
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 21] = [
+    "==", "!=", "<=", ">=", "=~", "!~", "&&", "||", "=", "<", ">", "!", "(", ")", ",",
+    "+", "-", "*", "/", "[", "]",
+];
+
+impl FieldPath {
+    pub(crate) fn key(key: &str) -> FieldPath {
+        FieldPath { segments: vec![Segment::Key(key.to_string())] }
+    }
+    pub(crate) fn parse(string: &str) -> Result<FieldPath, Error> {
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut key = String::new();
+        let mut chars = string.chars();
+        while let Some(char) = chars.next() {
+            match char {
+                '.' => {
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+                }
+                '[' => {
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+                    let index: String = chars.by_ref().take_while(|char| *char != ']').collect();
+                    let index = index.trim().parse::<usize>().map_err(|error| {
+                        Error::wrap(format!("Invalid index in field path '{}'", string), error)
+                    })?;
+                    segments.push(Segment::Index(index));
+                }
+                _ => key.push(char),
+            }
+        }
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+        if segments.is_empty() {
+            Err(Error::from(format!("Invalid field path '{}'", string)))
+        } else {
+            Ok(FieldPath { segments })
+        }
+    }
+    pub(crate) fn resolve<'a>(&self, map: &'a Map<String, Value>) -> Option<&'a Value> {
+        let mut segments = self.segments.iter();
+        let mut value = match segments.next()? {
+            Segment::Key(key) => map.get(key)?,
+            Segment::Index(_) => return None,
+        };
+        for segment in segments {
+            value = match (segment, value) {
+                (Segment::Key(key), Value::Object(map)) => map.get(key)?,
+                (Segment::Index(index), Value::Array(array)) => array.get(*index)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+impl TryFrom<&str> for Function {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string.to_ascii_lowercase().as_str() {
+            "log10" => Ok(Function::Log10),
+            "ln" | "log" => Ok(Function::Ln),
+            "exp" => Ok(Function::Exp),
+            "abs" => Ok(Function::Abs),
+            "sqrt" => Ok(Function::Sqrt),
+            "round" => Ok(Function::Round),
+            "concat" => Ok(Function::Concat),
+            "lower" => Ok(Function::Lower),
+            "upper" => Ok(Function::Upper),
+            _ => Err(Error::from(format!(
+                "Unknown function '{}'. Known functions are 'log10', 'ln', 'exp', 'abs', \
+                'sqrt', 'round', 'concat', 'lower' and 'upper'.", string
+            ))),
+        }
+    }
+}
+
+impl ValueExpr {
+    pub(crate) fn evaluate<'a>(&'a self, map: &'a Map<String, Value>) -> Cow<'a, Value> {
+        match self {
+            ValueExpr::Path(path) => match path.resolve(map) {
+                Some(value) => Cow::Borrowed(value),
+                None => Cow::Owned(Value::Null),
+            },
+            ValueExpr::Literal(value) => Cow::Borrowed(value),
+            ValueExpr::Neg(expr) => {
+                Cow::Owned(number_value(as_number(&expr.evaluate(map)).map(|x| -x)))
+            }
+            ValueExpr::Arith(lhs, op, rhs) => {
+                let lhs = lhs.evaluate(map);
+                let rhs = rhs.evaluate(map);
+                Cow::Owned(evaluate_arith(&lhs, *op, &rhs))
+            }
+            ValueExpr::Call(function, args) => {
+                let args: Vec<Cow<Value>> = args.iter().map(|arg| arg.evaluate(map)).collect();
+                Cow::Owned(evaluate_call(*function, &args))
+            }
+        }
+    }
+}
+
+fn evaluate_arith(lhs: &Value, op: ArithOp, rhs: &Value) -> Value {
+    if lhs.is_null() || rhs.is_null() {
+        return Value::Null;
+    }
+    match (as_number(lhs), as_number(rhs)) {
+        (Some(lhs), Some(rhs)) => {
+            let result = match op {
+                ArithOp::Add => lhs + rhs,
+                ArithOp::Sub => lhs - rhs,
+                ArithOp::Mul => lhs * rhs,
+                ArithOp::Div => lhs / rhs,
+            };
+            number_value(Some(result))
+        }
+        _ => match op {
+            ArithOp::Add => Value::String(format!("{}{}", as_string(lhs), as_string(rhs))),
+            _ => Value::Null,
+        },
+    }
+}
+
+fn evaluate_call(function: Function, args: &[Cow<Value>]) -> Value {
+    let number = || args.first().and_then(|arg| as_number(arg));
+    match function {
+        Function::Log10 => number_value(number().map(f64::log10)),
+        Function::Ln => number_value(number().map(f64::ln)),
+        Function::Exp => number_value(number().map(f64::exp)),
+        Function::Abs => number_value(number().map(f64::abs)),
+        Function::Sqrt => number_value(number().map(f64::sqrt)),
+        Function::Round => {
+            let digits = args.get(1).and_then(|arg| as_number(arg)).unwrap_or(0.0) as i32;
+            let factor = 10f64.powi(digits);
+            number_value(number().map(|x| (x * factor).round() / factor))
+        }
+        Function::Concat => {
+            let string: String =
+                args.iter().filter(|arg| !arg.is_null()).map(|arg| as_string(arg)).collect();
+            Value::String(string)
+        }
+        Function::Lower => match args.first().filter(|arg| !arg.is_null()) {
+            Some(arg) => Value::String(as_string(arg).to_lowercase()),
+            None => Value::Null,
+        },
+        Function::Upper => match args.first().filter(|arg| !arg.is_null()) {
+            Some(arg) => Value::String(as_string(arg).to_uppercase()),
+            None => Value::Null,
+        },
+    }
+}
+
+fn number_value(number: Option<f64>) -> Value {
+    number.map(Value::from).unwrap_or(Value::Null)
+}
+
+pub(crate) fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse::<f64>().ok(),
+        Value::Bool(bool) => Some(if *bool { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+pub(crate) fn as_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn tokenize(string: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = string.chars().peekable();
+    while let Some(&char) = chars.peek() {
+        if char.is_whitespace() {
+            chars.next();
+        } else if char == '"' || char == '\'' {
+            chars.next();
+            tokens.push(Token::Str(read_quoted(&mut chars, char)?));
+        } else if char == '`' {
+            chars.next();
+            tokens.push(Token::Field(read_quoted(&mut chars, char)?));
+        } else if char.is_ascii_digit() || char == '.' {
+            let mut number = String::new();
+            while let Some(&char) = chars.peek() {
+                let is_exponent_sign =
+                    (char == '-' || char == '+') && number.ends_with(['e', 'E']);
+                if char.is_ascii_alphanumeric() || char == '.' || is_exponent_sign {
+                    number.push(char);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let value = number.parse::<f64>().map_err(|_| {
+                Error::from(format!("Invalid number '{}' in expression '{}'", number, string))
+            })?;
+            tokens.push(Token::Num(value));
+        } else if char.is_alphabetic() || char == '_' {
+            let mut ident = String::new();
+            while let Some(&char) = chars.peek() {
+                if char.is_alphanumeric() || char == '_' || char == '.' {
+                    ident.push(char);
+                    chars.next();
+                } else if char == '[' {
+                    for char in chars.by_ref() {
+                        ident.push(char);
+                        if char == ']' {
+                            break;
+                        }
+                    }
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(ident));
+        } else {
+            let rest: String = chars.clone().collect();
+            let symbol =
+                SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)).ok_or_else(|| {
+                    Error::from(format!(
+                        "Unexpected character '{}' in expression '{}'", char, string
+                    ))
+                })?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Sym(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut Peekable<Chars>, quote: char) -> Result<String, Error> {
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            None => return Err(Error::from(format!(
+                "Unterminated {}-quoted string in expression", quote
+            ))),
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some(char) => string.push(char),
+                None => return Err(Error::from("Dangling escape in expression")),
+            },
+            Some(char) if char == quote => return Ok(string),
+            Some(char) => string.push(char),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(ident) => format!("'{}'", ident),
+        Token::Field(field) => format!("`{}`", field),
+        Token::Str(string) => format!("\"{}\"", string),
+        Token::Num(number) => format!("{}", number),
+        Token::Sym(symbol) => format!("'{}'", symbol),
+    }
+}
+
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    pub(crate) pos: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(string: &str) -> Result<Parser, Error> {
+        let tokens = tokenize(string)?;
+        Ok(Parser { tokens, pos: 0 })
+    }
+    pub(crate) fn peek(&self) -> Option<&Token> { self.tokens.get(self.pos) }
+    pub(crate) fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+    pub(crate) fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    pub(crate) fn next_is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword))
+    }
+    pub(crate) fn next_is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Sym(sym)) if *sym == symbol)
+    }
+    pub(crate) fn expect_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.next_is_symbol(symbol) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", symbol)))
+        }
+    }
+    pub(crate) fn expect_end(&self) -> Result<(), Error> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(Error::from(format!(
+                "Unexpected {} in expression", describe(token)
+            ))),
+        }
+    }
+    pub(crate) fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(token) => Error::from(format!(
+                "Expected {} in expression, but got {}", expected, describe(token)
+            )),
+            None => Error::from(format!(
+                "Expected {} in expression, but got end of expression", expected
+            )),
+        }
+    }
+    pub(crate) fn parse_value(&mut self) -> Result<ValueExpr, Error> {
+        let mut expr = self.parse_term()?;
+        loop {
+            let op = if self.next_is_symbol("+") {
+                ArithOp::Add
+            } else if self.next_is_symbol("-") {
+                ArithOp::Sub
+            } else {
+                break;
+            };
+            self.pos += 1;
+            let rhs = self.parse_term()?;
+            expr = ValueExpr::Arith(Box::new(expr), op, Box::new(rhs));
+        }
+        Ok(expr)
+    }
+    fn parse_term(&mut self) -> Result<ValueExpr, Error> {
+        let mut expr = self.parse_factor()?;
+        loop {
+            let op = if self.next_is_symbol("*") {
+                ArithOp::Mul
+            } else if self.next_is_symbol("/") {
+                ArithOp::Div
+            } else {
+                break;
+            };
+            self.pos += 1;
+            let rhs = self.parse_factor()?;
+            expr = ValueExpr::Arith(Box::new(expr), op, Box::new(rhs));
+        }
+        Ok(expr)
+    }
+    fn parse_factor(&mut self) -> Result<ValueExpr, Error> {
+        if self.next_is_symbol("-") {
+            self.pos += 1;
+            let expr = self.parse_factor()?;
+            return Ok(match expr {
+                ValueExpr::Literal(Value::Number(number)) => {
+                    ValueExpr::Literal(number_value(number.as_f64().map(|x| -x)))
+                }
+                expr => ValueExpr::Neg(Box::new(expr)),
+            });
+        }
+        if self.next_is_symbol("(") {
+            self.pos += 1;
+            let expr = self.parse_value()?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+        match self.next() {
+            Some(Token::Str(string)) => Ok(ValueExpr::Literal(Value::String(string))),
+            Some(Token::Num(number)) => Ok(ValueExpr::Literal(Value::from(number))),
+            Some(Token::Field(field)) => Ok(ValueExpr::Path(FieldPath::key(&field))),
+            Some(Token::Ident(ident)) => {
+                if self.next_is_symbol("(") {
+                    let function = Function::try_from(ident.as_str())?;
+                    let args = self.parse_list()?;
+                    Ok(ValueExpr::Call(function, args))
+                } else if ident.eq_ignore_ascii_case("null") {
+                    Ok(ValueExpr::Literal(Value::Null))
+                } else if ident.eq_ignore_ascii_case("true") {
+                    Ok(ValueExpr::Literal(Value::Bool(true)))
+                } else if ident.eq_ignore_ascii_case("false") {
+                    Ok(ValueExpr::Literal(Value::Bool(false)))
+                } else {
+                    Ok(ValueExpr::Path(FieldPath::parse(&ident)?))
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("field name or value"))
+            }
+        }
+    }
+    pub(crate) fn parse_list(&mut self) -> Result<Vec<ValueExpr>, Error> {
+        self.expect_symbol("(")?;
+        let mut list: Vec<ValueExpr> = Vec::new();
+        if !self.next_is_symbol(")") {
+            list.push(self.parse_value()?);
+            while self.next_is_symbol(",") {
+                self.pos += 1;
+                list.push(self.parse_value()?);
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn evaluate(expr: &str, record: Value) -> Value {
+        let mut parser = Parser::new(expr).unwrap();
+        let expr = parser.parse_value().unwrap();
+        parser.expect_end().unwrap();
+        expr.evaluate(record.as_object().unwrap()).into_owned()
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(evaluate("1 + 2 * 3", json!({})), json!(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3", json!({})), json!(9.0));
+        assert_eq!(evaluate("10 - 4 - 3", json!({})), json!(3.0));
+        assert_eq!(evaluate("12 / 2 / 3", json!({})), json!(2.0));
+        assert_eq!(evaluate("-2 * -x", json!({ "x": 3 })), json!(6.0));
+    }
+
+    #[test]
+    fn fields_are_resolved_by_path() {
+        let record = json!({ "a": { "b": [{ "c": 1 }, { "c": 2 }] }, "p value": 0.5 });
+        assert_eq!(evaluate("a.b[1].c", record.clone()), json!(2));
+        assert_eq!(evaluate("a.b[2].c", record.clone()), Value::Null);
+        assert_eq!(evaluate("`p value` * 2", record.clone()), json!(1.0));
+        assert_eq!(evaluate("missing + 1", record), Value::Null);
+    }
+
+    #[test]
+    fn functions_and_literals() {
+        assert_eq!(evaluate("log10(x)", json!({ "x": 1000 })), json!(3.0));
+        assert_eq!(evaluate("round(x, 2)", json!({ "x": 1.2345 })), json!(1.23));
+        assert_eq!(evaluate("concat(a, '-', b)", json!({ "a": "x", "b": 1 })), json!("x-1"));
+        assert_eq!(evaluate("upper(\"a\\tb\")", json!({})), json!("A\tB"));
+        assert_eq!(evaluate("1e-3 * 1000", json!({})), json!(1.0));
+    }
+
+    #[test]
+    fn invalid_expressions_are_errors() {
+        assert!(Parser::new("'unterminated").is_err());
+        assert!(Parser::new("a # b").is_err());
+        assert!(Parser::new("1kg_af").is_err());
+        assert!(Parser::new("nope(1)").unwrap().parse_value().is_err());
+        let mut parser = Parser::new("a b").unwrap();
+        parser.parse_value().unwrap();
+        assert!(parser.expect_end().is_err());
+    }
+}
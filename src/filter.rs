@@ -0,0 +1,308 @@
+use crate::error::Error;
+use crate::expr::{as_number, as_string, Parser, Token, ValueExpr};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+pub(crate) struct Filter {
+    expr: Expr,
+}
+
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(ValueExpr, CompareOp, ValueExpr),
+    In(ValueExpr, Vec<ValueExpr>),
+    Matches(ValueExpr, Regex),
+    IsNull(ValueExpr),
+    Truthy(ValueExpr),
+}
+
+#[derive(Copy, Clone)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Filter {
+    pub(crate) fn parse(string: &str) -> Result<Filter, Error> {
+        let mut parser = Parser::new(string)?;
+        let expr = parse_or(&mut parser).and_then(|expr| {
+            parser.expect_end()?;
+            Ok(expr)
+        }).map_err(|error| {
+            Error::wrap(format!("Invalid filter expression '{}'", string), error)
+        })?;
+        Ok(Filter { expr })
+    }
+    pub(crate) fn matches(&self, map: &Map<String, Value>) -> bool {
+        self.expr.evaluate(map)
+    }
+    pub(crate) fn matches_line(&self, line: &str) -> Result<bool, Error> {
+        let value: Value = serde_json::from_str(line)?;
+        match &value {
+            Value::Object(map) => Ok(self.matches(map)),
+            _ => Ok(false),
+        }
+    }
+}
+
+fn parse_or(parser: &mut Parser) -> Result<Expr, Error> {
+    let mut expr = parse_and(parser)?;
+    while parser.next_is_keyword("or") || parser.next_is_symbol("||") {
+        parser.pos += 1;
+        let rhs = parse_and(parser)?;
+        expr = Expr::Or(Box::new(expr), Box::new(rhs));
+    }
+    Ok(expr)
+}
+
+fn parse_and(parser: &mut Parser) -> Result<Expr, Error> {
+    let mut expr = parse_unary(parser)?;
+    while parser.next_is_keyword("and") || parser.next_is_symbol("&&") {
+        parser.pos += 1;
+        let rhs = parse_unary(parser)?;
+        expr = Expr::And(Box::new(expr), Box::new(rhs));
+    }
+    Ok(expr)
+}
+
+fn parse_unary(parser: &mut Parser) -> Result<Expr, Error> {
+    if parser.next_is_keyword("not") || parser.next_is_symbol("!") {
+        parser.pos += 1;
+        let expr = parse_unary(parser)?;
+        Ok(Expr::Not(Box::new(expr)))
+    } else {
+        parse_primary(parser)
+    }
+}
+
+fn parse_primary(parser: &mut Parser) -> Result<Expr, Error> {
+    if parser.next_is_symbol("(") {
+        // Parentheses may group either a value like `(a + b) > 1` or a condition like
+        // `(a > 1 or b > 1)`, so try the former first and fall back to the latter.
+        let start = parser.pos;
+        if let Ok(expr) = parse_predicate(parser)
+            && !matches!(expr, Expr::Truthy(_)) {
+            return Ok(expr);
+        }
+        parser.pos = start + 1;
+        let expr = parse_or(parser)?;
+        parser.expect_symbol(")")?;
+        return Ok(expr);
+    }
+    parse_predicate(parser)
+}
+
+fn parse_predicate(parser: &mut Parser) -> Result<Expr, Error> {
+    let operand = parser.parse_value()?;
+    if let Some(Token::Sym(symbol)) = parser.peek() {
+        let op = match *symbol {
+            "==" | "=" => Some(CompareOp::Eq),
+            "!=" => Some(CompareOp::Ne),
+            "<" => Some(CompareOp::Lt),
+            "<=" => Some(CompareOp::Le),
+            ">" => Some(CompareOp::Gt),
+            ">=" => Some(CompareOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            parser.pos += 1;
+            let rhs = parser.parse_value()?;
+            return Ok(Expr::Compare(operand, op, rhs));
+        }
+        if *symbol == "=~" || *symbol == "!~" {
+            let negate = *symbol == "!~";
+            parser.pos += 1;
+            let regex = parse_regex(parser)?;
+            let expr = Expr::Matches(operand, regex);
+            return Ok(if negate { Expr::Not(Box::new(expr)) } else { expr });
+        }
+    }
+    if parser.next_is_keyword("is") {
+        parser.pos += 1;
+        let negate = parser.next_is_keyword("not");
+        if negate {
+            parser.pos += 1;
+        }
+        if !parser.next_is_keyword("null") {
+            return Err(parser.unexpected("'null'"));
+        }
+        parser.pos += 1;
+        let expr = Expr::IsNull(operand);
+        return Ok(if negate { Expr::Not(Box::new(expr)) } else { expr });
+    }
+    let negate_in = parser.next_is_keyword("not") &&
+        matches!(parser.peek_at(1),
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case("in"));
+    if negate_in {
+        parser.pos += 1;
+    }
+    if parser.next_is_keyword("in") {
+        parser.pos += 1;
+        let list = parser.parse_list()?;
+        let expr = Expr::In(operand, list);
+        return Ok(if negate_in { Expr::Not(Box::new(expr)) } else { expr });
+    }
+    Ok(Expr::Truthy(operand))
+}
+
+fn parse_regex(parser: &mut Parser) -> Result<Regex, Error> {
+    match parser.next() {
+        Some(Token::Str(pattern)) => Regex::new(&pattern).map_err(|error| {
+            Error::wrap(format!("Invalid regular expression '{}'", pattern), error)
+        }),
+        _ => {
+            parser.pos -= 1;
+            Err(parser.unexpected("quoted regular expression"))
+        }
+    }
+}
+
+impl Expr {
+    fn evaluate(&self, map: &Map<String, Value>) -> bool {
+        match self {
+            Expr::Or(lhs, rhs) => lhs.evaluate(map) || rhs.evaluate(map),
+            Expr::And(lhs, rhs) => lhs.evaluate(map) && rhs.evaluate(map),
+            Expr::Not(expr) => !expr.evaluate(map),
+            Expr::Compare(lhs, op, rhs) => {
+                let ordering = compare_values(&lhs.evaluate(map), &rhs.evaluate(map));
+                match (op, ordering) {
+                    (CompareOp::Eq, ordering) => ordering == Some(Ordering::Equal),
+                    (CompareOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+                    (_, None) => false,
+                    (CompareOp::Lt, Some(ordering)) => ordering.is_lt(),
+                    (CompareOp::Le, Some(ordering)) => ordering.is_le(),
+                    (CompareOp::Gt, Some(ordering)) => ordering.is_gt(),
+                    (CompareOp::Ge, Some(ordering)) => ordering.is_ge(),
+                }
+            }
+            Expr::In(operand, list) => {
+                let value = operand.evaluate(map);
+                list.iter().any(|item| {
+                    compare_values(&value, &item.evaluate(map)) == Some(Ordering::Equal)
+                })
+            }
+            Expr::Matches(operand, regex) => {
+                match operand.evaluate(map).as_ref() {
+                    Value::Null => false,
+                    Value::String(string) => regex.is_match(string),
+                    value => regex.is_match(&value.to_string()),
+                }
+            }
+            Expr::IsNull(operand) => operand.evaluate(map).is_null(),
+            Expr::Truthy(operand) => is_truthy(&operand.evaluate(map)),
+        }
+    }
+}
+
+fn compare_values(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        _ => match (as_number(lhs), as_number(rhs)) {
+            (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs),
+            _ => Some(as_string(lhs).cmp(&as_string(rhs))),
+        },
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(bool) => *bool,
+        Value::Number(number) => number.as_f64().is_some_and(|number| number != 0.0),
+        Value::String(string) => !string.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn matches(filter: &str, value: Value) -> bool {
+        Filter::parse(filter).unwrap().matches(value.as_object().unwrap())
+    }
+
+    #[test]
+    fn numeric_strings_compare_as_numbers() {
+        assert!(matches("n > 9", json!({ "n": "10" })));
+        assert!(matches("n > '9'", json!({ "n": "10" })));
+        assert!(!matches("n < 9", json!({ "n": "10" })));
+        assert!(matches("n == 10", json!({ "n": "10.0" })));
+    }
+
+    #[test]
+    fn other_strings_compare_as_strings() {
+        assert!(matches("name < 'b'", json!({ "name": "abc" })));
+        assert!(matches("name > '9'", json!({ "name": "a" })));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let record = json!({ "a": 1, "b": 0, "c": 1 });
+        assert!(matches("a == 1 or b == 1 and c == 0", record.clone()));
+        assert!(!matches("(a == 1 or b == 1) and c == 0", record.clone()));
+        assert!(matches("a == 1 && (b == 1 || c == 1)", record));
+    }
+
+    #[test]
+    fn parentheses_group_values_and_conditions() {
+        assert!(matches("(a + b) * 2 > 5", json!({ "a": 1, "b": 2 })));
+        assert!(!matches("(a > 5 or b > 5)", json!({ "a": 1, "b": 2 })));
+    }
+
+    #[test]
+    fn not_negates() {
+        assert!(matches("not a > 1", json!({ "a": 1 })));
+        assert!(matches("!(a > 1)", json!({ "a": 1 })));
+        assert!(!matches("not not a > 1", json!({ "a": 1 })));
+    }
+
+    #[test]
+    fn in_and_not_in() {
+        let record = json!({ "gene": "BRCA1", "n": 3 });
+        assert!(matches("gene in ('BRCA1', 'TP53')", record.clone()));
+        assert!(!matches("gene not in ('BRCA1', 'TP53')", record.clone()));
+        assert!(matches("n in (1, 2, 3)", record.clone()));
+        assert!(matches("missing not in (1)", record));
+    }
+
+    #[test]
+    fn regex_matches() {
+        let record = json!({ "id": "MONDO:0005148", "n": 42 });
+        assert!(matches("id =~ '^MONDO:'", record.clone()));
+        assert!(matches("id !~ '^EFO:'", record.clone()));
+        assert!(matches("n =~ '^4'", record.clone()));
+        assert!(!matches("missing =~ '.*'", record));
+        assert!(Filter::parse("id =~ '('").is_err());
+    }
+
+    #[test]
+    fn null_checks_and_truthiness() {
+        let record = json!({ "a": null, "b": "", "c": "x", "d": 0 });
+        assert!(matches("a is null", record.clone()));
+        assert!(matches("c is not null", record.clone()));
+        assert!(matches("missing is null", record.clone()));
+        assert!(!matches("b", record.clone()));
+        assert!(matches("c", record.clone()));
+        assert!(!matches("d", record.clone()));
+        assert!(!matches("a > 1", record.clone()));
+        assert!(matches("a != 1", record));
+    }
+
+    #[test]
+    fn backticked_keys_and_paths() {
+        let record = json!({ "p-value": 0.01, "a": { "b": [{ "c": "x" }] } });
+        assert!(matches("`p-value` < 0.05", record.clone()));
+        assert!(matches("a.b[0].c == 'x'", record));
+    }
+}
@@ -0,0 +1,590 @@
+use crate::error::Error;
+use crate::expr::{as_string, FieldPath};
+use crate::filter::Filter;
+use crate::io;
+use crate::json;
+use crate::location;
+use crate::location::DataLocation;
+use crate::pipe::{NextSummary, Summary};
+use crate::runtime::Runtime;
+use crate::tabular::{ColumnSpec, TableWriter, TabularOptions};
+use serde_json::{Map, Value};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+
+mod hows {
+    pub(crate) const INNER: &str = "inner";
+    pub(crate) const LEFT: &str = "left";
+}
+
+const LEFT_KEY: &str = "left";
+const RIGHT_KEY: &str = "right";
+const DEFAULT_MEMORY_LIMIT_MB: u64 = 512;
+
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum JoinHow {
+    Inner,
+    Left,
+}
+
+pub(crate) struct JoinOptions {
+    pub(crate) left_keys: Vec<String>,
+    pub(crate) right_keys: Vec<String>,
+    pub(crate) how: JoinHow,
+    pub(crate) memory_limit_mb: u64,
+    pub(crate) tabular: TabularOptions,
+}
+
+struct JoinKeys {
+    left: Vec<FieldPath>,
+    right: Vec<FieldPath>,
+}
+
+enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, PartialEq)]
+enum Strategy {
+    HashBuildRight,
+    HashBuildLeft,
+    SortMerge,
+}
+
+impl TryFrom<&str> for JoinHow {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            hows::INNER => Ok(JoinHow::Inner),
+            hows::LEFT => Ok(JoinHow::Left),
+            _ => Err(Error::from(format!(
+                "Unknown join type '{}'. Known join types are '{}' and '{}'.", string,
+                hows::INNER, hows::LEFT
+            ))),
+        }
+    }
+}
+
+impl JoinOptions {
+    pub(crate) fn new() -> JoinOptions {
+        JoinOptions {
+            left_keys: Vec::new(),
+            right_keys: Vec::new(),
+            how: JoinHow::Inner,
+            memory_limit_mb: DEFAULT_MEMORY_LIMIT_MB,
+            tabular: TabularOptions::new(),
+        }
+    }
+    pub(crate) fn add_keys(&mut self, keys: &str) {
+        for key in keys.split(',') {
+            match key.split_once('=') {
+                Some((left_key, right_key)) => {
+                    self.left_keys.push(left_key.trim().to_string());
+                    self.right_keys.push(right_key.trim().to_string());
+                }
+                None => {
+                    self.left_keys.push(key.trim().to_string());
+                    self.right_keys.push(key.trim().to_string());
+                }
+            }
+        }
+    }
+    fn join_keys(&self) -> Result<JoinKeys, Error> {
+        if self.left_keys.is_empty() {
+            return Err(Error::from("No join keys provided. Use --on <key>[,<key>...]."));
+        }
+        let left =
+            self.left_keys.iter().map(|key| FieldPath::parse(key))
+                .collect::<Result<Vec<FieldPath>, Error>>()?;
+        let right =
+            self.right_keys.iter().map(|key| FieldPath::parse(key))
+                .collect::<Result<Vec<FieldPath>, Error>>()?;
+        Ok(JoinKeys { left, right })
+    }
+}
+
+impl JoinKeys {
+    fn paths(&self, side: &Side) -> &[FieldPath] {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+    fn key(&self, side: &Side, map: &Map<String, Value>) -> Option<String> {
+        let mut key = String::new();
+        for (i, path) in self.paths(side).iter().enumerate() {
+            let value = path.resolve(map).filter(|value| !value.is_null())?;
+            if i > 0 {
+                key.push('\u{1f}');
+            }
+            key.push_str(&escape_key(&as_string(value)));
+        }
+        Some(key)
+    }
+}
+
+fn escape_key(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+struct JoinOutput<'a> {
+    columns: Vec<ColumnSpec>,
+    filter: Option<&'a Filter>,
+    table_writer: TableWriter,
+    n_rows: u64,
+}
+
+impl JoinOutput<'_> {
+    fn emit(&mut self, left: &Map<String, Value>, right: Option<&Map<String, Value>>)
+        -> Result<(), Error> {
+        let joined = join_maps(left, right);
+        if self.filter.is_none_or(|filter| filter.matches(&joined)) {
+            let row: Vec<Value> =
+                self.columns.iter().map(|column| column.evaluate(&joined)).collect();
+            self.table_writer.write_row(&row)?;
+            self.n_rows += 1;
+        }
+        Ok(())
+    }
+}
+
+fn join_maps(left: &Map<String, Value>, right: Option<&Map<String, Value>>)
+    -> Map<String, Value> {
+    let mut joined = left.clone();
+    if let Some(right) = right {
+        for (key, value) in right {
+            if !joined.contains_key(key) {
+                joined.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    joined.insert(LEFT_KEY.to_string(), Value::Object(left.clone()));
+    joined.insert(RIGHT_KEY.to_string(),
+                  right.map(|right| Value::Object(right.clone())).unwrap_or(Value::Null));
+    joined
+}
+
+pub(crate) fn join(runtime: &Runtime, left: &DataLocation, right: &DataLocation,
+                   options: &JoinOptions)
+    -> Result<(), Error> {
+    let keys = options.join_keys()?;
+    let columns =
+        if options.tabular.columns.is_empty() {
+            default_columns(runtime, left, right, options)?
+        } else {
+            options.tabular.columns.iter().map(|column| ColumnSpec::parse(column))
+                .collect::<Result<Vec<ColumnSpec>, Error>>()?
+        };
+    let names = columns.iter().map(|column| column.name.clone()).collect();
+    let tabular = &options.tabular;
+    let table_writer = TableWriter::new(tabular.format, names, tabular.output.as_deref())?;
+    let mut output =
+        JoinOutput { columns, filter: tabular.filter.as_ref(), table_writer, n_rows: 0 };
+    let memory_limit = options.memory_limit_mb * 1024 * 1024;
+    let left_size = location::size(runtime, left)?;
+    let right_size = location::size(runtime, right)?;
+    match choose_strategy(left_size, right_size, memory_limit, options.how) {
+        Strategy::HashBuildRight => {
+            eprintln!("Hash join, building from {} ({} bytes)", right, right_size);
+            hash_join(runtime, right, Side::Right, left, &keys, options.how, &mut output)?;
+        }
+        Strategy::HashBuildLeft => {
+            eprintln!("Hash join, building from {} ({} bytes)", left, left_size);
+            hash_join(runtime, left, Side::Left, right, &keys, options.how, &mut output)?;
+        }
+        Strategy::SortMerge => {
+            eprintln!("Sort-merge join, since neither side fits into {} MB",
+                      options.memory_limit_mb);
+            sort_merge_join(runtime, left, right, &keys, options, &mut output)?;
+        }
+    }
+    eprintln!("Joined rows: {}", output.n_rows);
+    output.table_writer.finish()
+}
+
+/// Builds a hash table from the smaller side that fits into memory, which for a left join can
+/// only be the right side, and otherwise sorts both sides.
+fn choose_strategy(left_size: u64, right_size: u64, memory_limit: u64, how: JoinHow)
+    -> Strategy {
+    if right_size <= memory_limit && (how == JoinHow::Left || right_size <= left_size) {
+        Strategy::HashBuildRight
+    } else if left_size <= memory_limit && how == JoinHow::Inner {
+        Strategy::HashBuildLeft
+    } else {
+        Strategy::SortMerge
+    }
+}
+
+fn default_columns(runtime: &Runtime, left: &DataLocation, right: &DataLocation,
+                   options: &JoinOptions)
+    -> Result<Vec<ColumnSpec>, Error> {
+    let left_fields = json::get_fields(runtime, left)?;
+    let right_fields = json::get_fields(runtime, right)?;
+    let mut columns: Vec<ColumnSpec> = Vec::new();
+    for field in &left_fields {
+        columns.push(ColumnSpec::field(field));
+    }
+    for field in &right_fields {
+        if options.right_keys.contains(field) {
+            continue;
+        }
+        if left_fields.contains(field) {
+            columns.push(ColumnSpec::parse(&format!("{}.`{}`", RIGHT_KEY, field))?);
+        } else {
+            columns.push(ColumnSpec::field(field));
+        }
+    }
+    Ok(columns)
+}
+
+struct HashBuildSummary<'a> {
+    keys: &'a JoinKeys,
+    side: Side,
+    table: HashMap<String, Vec<Map<String, Value>>>,
+}
+
+impl Summary for HashBuildSummary<'_> {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let map = json::as_json_obj(&line)?;
+        let HashBuildSummary { keys, side, mut table } = self;
+        if let Some(key) = keys.key(&side, &map) {
+            table.entry(key).or_default().push(map);
+        }
+        Ok(NextSummary { summary: HashBuildSummary { keys, side, table } })
+    }
+}
+
+struct HashProbeSummary<'a, 'b> {
+    keys: &'a JoinKeys,
+    build_side: Side,
+    how: JoinHow,
+    table: HashMap<String, Vec<Map<String, Value>>>,
+    output: &'a mut JoinOutput<'b>,
+}
+
+impl Summary for HashProbeSummary<'_, '_> {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let map = json::as_json_obj(&line)?;
+        let summary = self;
+        let probe_side = match summary.build_side {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        };
+        let matches =
+            summary.keys.key(&probe_side, &map).and_then(|key| summary.table.get(&key));
+        match (matches, &summary.build_side) {
+            (Some(matches), Side::Right) => {
+                for right in matches {
+                    summary.output.emit(&map, Some(right))?;
+                }
+            }
+            (Some(matches), Side::Left) => {
+                for left in matches {
+                    summary.output.emit(left, Some(&map))?;
+                }
+            }
+            (None, Side::Right) => {
+                if summary.how == JoinHow::Left {
+                    summary.output.emit(&map, None)?;
+                }
+            }
+            (None, Side::Left) => {}
+        }
+        Ok(NextSummary { summary })
+    }
+}
+
+fn hash_join(runtime: &Runtime, build: &DataLocation, build_side: Side, probe: &DataLocation,
+             keys: &JoinKeys, how: JoinHow, output: &mut JoinOutput) -> Result<(), Error> {
+    let build_summary = HashBuildSummary { keys, side: build_side, table: HashMap::new() };
+    let HashBuildSummary { side: build_side, table, .. } =
+        location::process_from(runtime, build, build_summary)?;
+    let probe_summary = HashProbeSummary { keys, build_side, how, table, output };
+    location::process_from(runtime, probe, probe_summary)?;
+    Ok(())
+}
+
+struct SortRunSummary<'a> {
+    keys: &'a JoinKeys,
+    side: Side,
+    dir: &'a Path,
+    budget: usize,
+    buffer: Vec<(String, String)>,
+    buffer_bytes: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl SortRunSummary<'_> {
+    fn flush_run(&mut self) -> Result<(), Error> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.buffer.sort();
+        let prefix = match self.side {
+            Side::Left => LEFT_KEY,
+            Side::Right => RIGHT_KEY,
+        };
+        let path = self.dir.join(format!("{}-{}.run", prefix, self.runs.len()));
+        let mut writer = BufWriter::new(io::create_file(&path)?);
+        for (key, line) in self.buffer.drain(..) {
+            writeln!(writer, "{}\t{}", key, line)?;
+        }
+        writer.flush()?;
+        self.buffer_bytes = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+}
+
+impl Summary for SortRunSummary<'_> {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let map = json::as_json_obj(&line)?;
+        let mut summary = self;
+        // Records without a complete key sort last and never match.
+        let key = match summary.keys.key(&summary.side, &map) {
+            Some(key) => format!("K{}", key),
+            None => "N".to_string(),
+        };
+        summary.buffer_bytes += key.len() + line.len();
+        summary.buffer.push((key, line));
+        if summary.buffer_bytes >= summary.budget {
+            summary.flush_run()?;
+        }
+        Ok(NextSummary { summary })
+    }
+}
+
+struct SortedRuns {
+    readers: Vec<Lines<BufReader<File>>>,
+    heap: BinaryHeap<Reverse<(String, usize, String)>>,
+}
+
+impl SortedRuns {
+    fn new(runs: &[PathBuf]) -> Result<SortedRuns, Error> {
+        let mut readers: Vec<Lines<BufReader<File>>> = Vec::new();
+        for run in runs {
+            readers.push(BufReader::new(io::open_file(run)?).lines());
+        }
+        let mut sorted_runs = SortedRuns { readers, heap: BinaryHeap::new() };
+        for i in 0..sorted_runs.readers.len() {
+            sorted_runs.refill(i)?;
+        }
+        Ok(sorted_runs)
+    }
+    fn refill(&mut self, i: usize) -> Result<(), Error> {
+        if let Some(line) = self.readers[i].next() {
+            let line = line?;
+            let (key, record) = line.split_once('\t').ok_or_else(|| {
+                Error::from(format!("Corrupt sort run record '{}'", line))
+            })?;
+            self.heap.push(Reverse((key.to_string(), i, record.to_string())));
+        }
+        Ok(())
+    }
+    fn next_record(&mut self) -> Result<Option<(String, String)>, Error> {
+        match self.heap.pop() {
+            Some(Reverse((key, i, record))) => {
+                self.refill(i)?;
+                Ok(Some((key, record)))
+            }
+            None => Ok(None),
+        }
+    }
+    fn peek_key(&self) -> Option<&str> {
+        self.heap.peek().map(|Reverse((key, _, _))| key.as_str())
+    }
+}
+
+fn sort_runs(runtime: &Runtime, location: &DataLocation, side: Side, keys: &JoinKeys, dir: &Path,
+             budget: usize) -> Result<Vec<PathBuf>, Error> {
+    let summary = SortRunSummary {
+        keys, side, dir, budget, buffer: Vec::new(), buffer_bytes: 0, runs: Vec::new()
+    };
+    let mut summary = location::process_from(runtime, location, summary)?;
+    summary.flush_run()?;
+    Ok(summary.runs)
+}
+
+fn sort_merge_join(runtime: &Runtime, left: &DataLocation, right: &DataLocation, keys: &JoinKeys,
+                   options: &JoinOptions, output: &mut JoinOutput) -> Result<(), Error> {
+    let dir = std::env::temp_dir().join(format!("lembic-join-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let result = sort_merge_join_in(runtime, left, right, keys, options, output, &dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+fn sort_merge_join_in(runtime: &Runtime, left: &DataLocation, right: &DataLocation, keys: &JoinKeys,
+                      options: &JoinOptions, output: &mut JoinOutput, dir: &Path)
+    -> Result<(), Error> {
+    let budget = (options.memory_limit_mb * 1024 * 1024 / 2) as usize;
+    let left_runs = sort_runs(runtime, left, Side::Left, keys, dir, budget)?;
+    let right_runs = sort_runs(runtime, right, Side::Right, keys, dir, budget)?;
+    merge_runs(&left_runs, &right_runs, options.how, output)
+}
+
+fn merge_runs(left_runs: &[PathBuf], right_runs: &[PathBuf], how: JoinHow,
+              output: &mut JoinOutput) -> Result<(), Error> {
+    let mut lefts = SortedRuns::new(left_runs)?;
+    let mut rights = SortedRuns::new(right_runs)?;
+    let mut group_key: Option<String> = None;
+    let mut group: Vec<Map<String, Value>> = Vec::new();
+    while let Some((left_key, left_record)) = lefts.next_record()? {
+        let left_map = json::as_json_obj(&left_record)?;
+        if !left_key.starts_with('K') {
+            if how == JoinHow::Left {
+                output.emit(&left_map, None)?;
+            }
+            continue;
+        }
+        if group_key.as_deref() != Some(left_key.as_str()) {
+            group.clear();
+            while rights.peek_key().is_some_and(|right_key| right_key < left_key.as_str()) {
+                rights.next_record()?;
+            }
+            while rights.peek_key() == Some(left_key.as_str()) {
+                if let Some((_, right_record)) = rights.next_record()? {
+                    group.push(json::as_json_obj(&right_record)?);
+                }
+            }
+            group_key = Some(left_key);
+        }
+        if group.is_empty() {
+            if how == JoinHow::Left {
+                output.emit(&left_map, None)?;
+            }
+        } else {
+            for right_map in &group {
+                output.emit(&left_map, Some(right_map))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tabular::TabularFormat;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    fn lefts() -> Vec<String> {
+        [
+            json!({ "id": 1, "a": "x" }), json!({ "id": 2, "a": "z" }),
+            json!({ "id": 1, "a": "y" }), json!({ "a": "w" }), json!({ "id": null, "a": "v" }),
+        ].iter().map(|value| value.to_string()).collect()
+    }
+
+    fn rights() -> Vec<String> {
+        [
+            json!({ "id": 1, "b": "q" }), json!({ "id": 3, "b": "r" }),
+            json!({ "id": 1, "b": "p" }), json!({ "b": "s" }),
+        ].iter().map(|value| value.to_string()).collect()
+    }
+
+    fn keys() -> JoinKeys {
+        JoinKeys { left: vec![FieldPath::key("id")], right: vec![FieldPath::key("id")] }
+    }
+
+    fn test_dir() -> PathBuf {
+        static N_DIRS: AtomicUsize = AtomicUsize::new(0);
+        let n_dir = N_DIRS.fetch_add(1, AtomicOrdering::Relaxed);
+        let dir =
+            std::env::temp_dir().join(format!("lembic-join-test-{}-{}", std::process::id(), n_dir));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn with_output<F>(dir: &Path, join: F) -> Vec<String>
+    where
+        F: FnOnce(&mut JoinOutput),
+    {
+        let path = dir.join("output.tsv");
+        let columns = vec![ColumnSpec::field("a"), ColumnSpec::field("b")];
+        let names = columns.iter().map(|column| column.name.clone()).collect();
+        let table_writer = TableWriter::new(TabularFormat::Tsv, names, Some(&path)).unwrap();
+        let mut output = JoinOutput { columns, filter: None, table_writer, n_rows: 0 };
+        join(&mut output);
+        output.table_writer.finish().unwrap();
+        let mut rows: Vec<String> =
+            std::fs::read_to_string(&path).unwrap().lines().skip(1).map(String::from).collect();
+        rows.sort();
+        std::fs::remove_dir_all(dir).unwrap();
+        rows
+    }
+
+    fn hash_join_lines(build_side: Side, how: JoinHow) -> Vec<String> {
+        let keys = keys();
+        let (build, probe) = match build_side {
+            Side::Left => (lefts(), rights()),
+            Side::Right => (rights(), lefts()),
+        };
+        with_output(&test_dir(), |output| {
+            let mut build_summary =
+                HashBuildSummary { keys: &keys, side: build_side, table: HashMap::new() };
+            for line in build {
+                build_summary = build_summary.next(line).unwrap().summary;
+            }
+            let HashBuildSummary { side: build_side, table, .. } = build_summary;
+            let mut probe_summary =
+                HashProbeSummary { keys: &keys, build_side, how, table, output };
+            for line in probe {
+                probe_summary = probe_summary.next(line).unwrap().summary;
+            }
+        })
+    }
+
+    fn sort_runs_from_lines(lines: Vec<String>, side: Side, keys: &JoinKeys, dir: &Path)
+        -> Vec<PathBuf> {
+        let mut summary = SortRunSummary {
+            keys, side, dir, budget: 1, buffer: Vec::new(), buffer_bytes: 0, runs: Vec::new()
+        };
+        for line in lines {
+            summary = summary.next(line).unwrap().summary;
+        }
+        summary.flush_run().unwrap();
+        summary.runs
+    }
+
+    fn sort_merge_join_lines(how: JoinHow) -> Vec<String> {
+        let keys = keys();
+        let dir = test_dir();
+        let left_runs = sort_runs_from_lines(lefts(), Side::Left, &keys, &dir);
+        let right_runs = sort_runs_from_lines(rights(), Side::Right, &keys, &dir);
+        assert_eq!((left_runs.len(), right_runs.len()), (5, 4));
+        with_output(&dir, |output| {
+            merge_runs(&left_runs, &right_runs, how, output).unwrap();
+        })
+    }
+
+    #[test]
+    fn inner_join_matches_duplicate_keys_and_skips_missing_ones() {
+        let expected = vec!["x\tp", "x\tq", "y\tp", "y\tq"];
+        assert_eq!(hash_join_lines(Side::Right, JoinHow::Inner), expected);
+        assert_eq!(hash_join_lines(Side::Left, JoinHow::Inner), expected);
+        assert_eq!(sort_merge_join_lines(JoinHow::Inner), expected);
+    }
+
+    #[test]
+    fn left_join_keeps_unmatched_and_keyless_left_records() {
+        let expected = vec!["v\t", "w\t", "x\tp", "x\tq", "y\tp", "y\tq", "z\t"];
+        assert_eq!(hash_join_lines(Side::Right, JoinHow::Left), expected);
+        assert_eq!(sort_merge_join_lines(JoinHow::Left), expected);
+    }
+
+    #[test]
+    fn strategy_depends_on_memory_limit() {
+        assert_eq!(choose_strategy(100, 50, 60, JoinHow::Inner), Strategy::HashBuildRight);
+        assert_eq!(choose_strategy(50, 100, 60, JoinHow::Inner), Strategy::HashBuildLeft);
+        assert_eq!(choose_strategy(50, 100, 60, JoinHow::Left), Strategy::SortMerge);
+        assert_eq!(choose_strategy(100, 100, 60, JoinHow::Inner), Strategy::SortMerge);
+        assert_eq!(choose_strategy(100, 50, 100, JoinHow::Left), Strategy::HashBuildRight);
+    }
+}
@@ -2,10 +2,12 @@ use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use serde_json::{Map, Value};
 use crate::error::Error;
+use crate::filter::Filter;
 use crate::pipe::{LinePipe, NextSummary, Summary};
 use crate::runtime::Runtime;
-use crate::s3;
-use crate::s3::S3Uri;
+use crate::location;
+use crate::location::DataLocation;
+use crate::tabular::{ColumnSpec, TableWriter, TabularOptions};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum JsonType {
@@ -87,26 +89,34 @@ impl Display for JsonSchema {
     }
 }
 
-pub(crate) fn print_schema(runtime: &Runtime, s3uri: &S3Uri) -> Result<(), Error> {
-    let pipe = JsonSchemaPipe::new(s3uri.clone());
-    let schema = s3::process(runtime, &pipe)?;
+pub(crate) fn print_schema(runtime: &Runtime, location: &DataLocation) -> Result<(), Error> {
+    let pipe = JsonSchemaPipe::new(location.clone());
+    let schema = location::process(runtime, &pipe)?;
     println!("{}", schema);
     Ok(())
 }
 
-pub(crate) fn print_tabular(runtime: &Runtime, s3uri: &S3Uri, columns: &[String])
+pub(crate) fn print_tabular(runtime: &Runtime, location: &DataLocation, options: &TabularOptions)
     -> Result<(), Error> {
     let columns =
-        if columns.is_empty() {
-            let schema_pipe = JsonSchemaPipe::new(s3uri.clone());
-            let schema = s3::process(runtime, &schema_pipe)?;
-            schema.fields
+        if options.columns.is_empty() {
+            get_fields(runtime, location)?.iter().map(|field| ColumnSpec::field(field)).collect()
         } else {
-            columns.to_vec()
+            options.columns.iter().map(|column| ColumnSpec::parse(column))
+                .collect::<Result<Vec<ColumnSpec>, Error>>()?
         };
-    let pipe = TabularPrinterPipe { s3uri: s3uri.clone(), columns };
-    s3::process(runtime, &pipe)?;
-    Ok(())
+    let names = columns.iter().map(|column| column.name.clone()).collect();
+    let table_writer = TableWriter::new(options.format, names, options.output.as_deref())?;
+    let summary =
+        TabularPrinterSummary { table_writer, columns, filter: options.filter.as_ref() };
+    let summary = location::process_from(runtime, location, summary)?;
+    summary.table_writer.finish()
+}
+
+pub(crate) fn get_fields(runtime: &Runtime, location: &DataLocation) -> Result<Vec<String>, Error> {
+    let schema_pipe = JsonSchemaPipe::new(location.clone());
+    let schema = location::process(runtime, &schema_pipe)?;
+    Ok(schema.fields)
 }
 
 fn get_json_type(value: &Value) -> JsonType {
@@ -121,11 +131,11 @@ fn get_json_type(value: &Value) -> JsonType {
 }
 
 struct JsonSchemaPipe {
-    s3uri: S3Uri
+    location: DataLocation
 }
 
 impl JsonSchemaPipe {
-    fn new(s3uri: S3Uri) -> JsonSchemaPipe { JsonSchemaPipe { s3uri } }
+    fn new(location: DataLocation) -> JsonSchemaPipe { JsonSchemaPipe { location } }
 }
 
 impl Summary for JsonSchema {
@@ -141,48 +151,31 @@ impl Summary for JsonSchema {
 impl LinePipe for JsonSchemaPipe {
     type Summary = JsonSchema;
 
-    fn s3uri(&self) -> &S3Uri { &self.s3uri }
+    fn location(&self) -> &DataLocation { &self.location }
 
     fn new_summary(&self) -> JsonSchema { JsonSchema::new() }
 }
 
-struct TabularPrinterSummary {
-    columns: Vec<String>,
-}
-struct TabularPrinterPipe {
-    s3uri: S3Uri,
-    columns: Vec<String>,
+struct TabularPrinterSummary<'a> {
+    table_writer: TableWriter,
+    columns: Vec<ColumnSpec>,
+    filter: Option<&'a Filter>,
 }
 
-impl Summary for TabularPrinterSummary {
+impl Summary for TabularPrinterSummary<'_> {
     fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
         let value: Value = serde_json::from_str(&line)?;
-        let summary = self;
-        if let Value::Object(map) = &value {
-            for column in &summary.columns {
-                if let Some(value) = map.get(column) {
-                    print!("{}\t", value);
-                } else {
-                    print!("\t");
-                }
-            }
-            println!();
+        let mut summary = self;
+        if let Value::Object(map) = &value
+            && summary.filter.is_none_or(|filter| filter.matches(map)) {
+            let row: Vec<Value> =
+                summary.columns.iter().map(|column| column.evaluate(map)).collect();
+            summary.table_writer.write_row(&row)?;
         }
         Ok(NextSummary { summary })
     }
 }
 
-impl LinePipe for TabularPrinterPipe {
-    type Summary = TabularPrinterSummary;
-
-    fn s3uri(&self) -> &S3Uri { &self.s3uri }
-
-    fn new_summary(&self) -> TabularPrinterSummary {
-        println!("#{}", self.columns.join("\t"));
-        TabularPrinterSummary { columns: self.columns.clone() }
-    }
-}
-
 pub(crate) fn as_json_obj(string: &str) -> Result<Map<String, Value>, Error> {
     let value: Value = serde_json::from_str(string)?;
     if let Value::Object(map) = value {
@@ -225,6 +218,16 @@ pub(crate) fn get_number(map: &Map<String, Value>, key: &str) -> Result<f64, Err
     }
 }
 
+pub(crate) fn get_bool(map: &Map<String, Value>, key: &str) -> Result<bool, Error> {
+    match map.get(key) {
+        Some(Value::Bool(bool)) => Ok(*bool),
+        Some(value) => {
+            Err(Error::from(format!("Expected boolean field '{}', but got {}", key, value)))
+        }
+        None => Err(Error::from(format!("Missing field '{}'", key))),
+    }
+}
+
 pub(crate) fn get_string_fallback(map: &Map<String, Value>, key: &str, key2: &str)
                                   -> Result<String, Error> {
     if let Some(value) = map.get(key).filter(|value| !value.is_null()) {
@@ -236,5 +239,19 @@ pub(crate) fn get_string_fallback(map: &Map<String, Value>, key: &str, key2: &st
     }
 }
 
-
-
+pub(crate) fn get_strings(map: &Map<String, Value>, key: &str) -> Result<Vec<String>, Error> {
+    match map.get(key) {
+        Some(Value::Array(values)) => {
+            values.iter().map(|value| match value {
+                Value::String(string) => Ok(string.clone()),
+                _ => Err(Error::from(format!(
+                    "Expected strings in field '{}', but got {}", key, value
+                ))),
+            }).collect()
+        }
+        Some(value) => {
+            Err(Error::from(format!("Expected array field '{}', but got {}", key, value)))
+        }
+        None => Ok(Vec::new()),
+    }
+}
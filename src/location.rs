@@ -0,0 +1,364 @@
+use crate::error::Error;
+use crate::io;
+use crate::pipe::{LinePipe, NextSummary, Summary};
+use crate::runtime::Runtime;
+use crate::s3;
+use crate::s3::S3Uri;
+use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+pub(crate) const RELEASE_PLACEHOLDER: &str = "{release}";
+
+mod formats {
+    pub(crate) const JSON_LINES: &str = "json-lines";
+    pub(crate) const TSV: &str = "tsv";
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum DataFormat {
+    JsonLines,
+    Tsv,
+}
+
+#[derive(Clone)]
+pub(crate) enum Location {
+    S3(S3Uri),
+    Local(PathBuf),
+}
+
+#[derive(Clone)]
+pub(crate) struct DataLocation {
+    pub(crate) location: Location,
+    pub(crate) format: DataFormat,
+}
+
+#[derive(Clone)]
+pub(crate) struct ObjectInfo {
+    pub(crate) name: String,
+    pub(crate) size: u64,
+    pub(crate) e_tag: Option<String>,
+    pub(crate) version: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+impl Display for DataFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataFormat::JsonLines => write!(f, "{}", formats::JSON_LINES),
+            DataFormat::Tsv => write!(f, "{}", formats::TSV),
+        }
+    }
+}
+
+impl TryFrom<&str> for DataFormat {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            formats::JSON_LINES | "jsonl" | "json" => Ok(DataFormat::JsonLines),
+            formats::TSV => Ok(DataFormat::Tsv),
+            _ => Err(Error::from(format!(
+                "Unknown data format '{}'. Known formats are '{}' and '{}'.", string,
+                formats::JSON_LINES, formats::TSV
+            ))),
+        }
+    }
+}
+
+impl Location {
+    pub(crate) fn parse(string: &str) -> Result<Location, Error> {
+        if string.starts_with("s3://") {
+            Ok(Location::S3(S3Uri::try_from(string)?))
+        } else {
+            Ok(Location::Local(PathBuf::from(string)))
+        }
+    }
+    pub(crate) fn needs_release(&self) -> bool {
+        self.to_string().contains(RELEASE_PLACEHOLDER)
+    }
+    pub(crate) fn with_release(&self, release: &str) -> Location {
+        match self {
+            Location::S3(s3uri) => {
+                let bucket = s3uri.bucket().replace(RELEASE_PLACEHOLDER, release);
+                let key = s3uri.key().replace(RELEASE_PLACEHOLDER, release);
+                Location::S3(S3Uri::new(bucket, key))
+            }
+            Location::Local(path) => {
+                let path = path.to_string_lossy().replace(RELEASE_PLACEHOLDER, release);
+                Location::Local(PathBuf::from(path))
+            }
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::S3(s3uri) => write!(f, "{}", s3uri),
+            Location::Local(path) => write!(f, "{}", path.to_string_lossy()),
+        }
+    }
+}
+
+impl DataLocation {
+    pub(crate) fn new(location: Location, format: DataFormat) -> DataLocation {
+        DataLocation { location, format }
+    }
+    pub(crate) fn s3(s3uri: S3Uri) -> DataLocation {
+        DataLocation::new(Location::S3(s3uri), DataFormat::JsonLines)
+    }
+    pub(crate) fn parse(string: &str) -> Result<DataLocation, Error> {
+        let location = Location::parse(string)?;
+        let format =
+            if string.ends_with(".tsv") { DataFormat::Tsv } else { DataFormat::JsonLines };
+        Ok(DataLocation::new(location, format))
+    }
+    pub(crate) fn needs_release(&self) -> bool { self.location.needs_release() }
+    pub(crate) fn with_release(&self, release: &str) -> DataLocation {
+        DataLocation::new(self.location.with_release(release), self.format)
+    }
+}
+
+impl Display for DataLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.format {
+            DataFormat::JsonLines => write!(f, "{}", self.location),
+            DataFormat::Tsv => write!(f, "{} ({})", self.location, self.format),
+        }
+    }
+}
+
+struct TsvSummary<S: Summary> {
+    header: Option<Vec<String>>,
+    inner: S,
+}
+
+impl<S: Summary> Summary for TsvSummary<S> {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let TsvSummary { header, inner } = self;
+        match header {
+            None => {
+                let header =
+                    line.trim_start_matches('#').split('\t').map(|field| field.to_string())
+                        .collect();
+                let inner = inner.skip_line();
+                Ok(NextSummary { summary: TsvSummary { header: Some(header), inner } })
+            }
+            Some(header) => {
+                let mut map = Map::new();
+                for (field, cell) in header.iter().zip(line.split('\t')) {
+                    map.insert(field.clone(), tsv_cell_value(cell));
+                }
+                let inner = inner.next(Value::Object(map).to_string())?.summary;
+                Ok(NextSummary { summary: TsvSummary { header: Some(header), inner } })
+            }
+        }
+    }
+    fn start_object(self, index: usize) -> Self {
+        TsvSummary { header: None, inner: self.inner.start_object(index) }
+    }
+}
+
+/// Integers stay integers and other finite numbers become floats. Tokens that would not
+/// survive as numbers, like IDs with leading zeros, stay strings.
+fn tsv_cell_value(cell: &str) -> Value {
+    if cell.is_empty() || cell == "NA" {
+        Value::Null
+    } else if has_leading_zero(cell) {
+        Value::String(cell.to_string())
+    } else if let Ok(integer) = cell.parse::<i64>() {
+        Value::from(integer)
+    } else if let Ok(number) = cell.parse::<f64>()
+        && number.is_finite() {
+        Value::from(number)
+    } else {
+        Value::String(cell.to_string())
+    }
+}
+
+fn has_leading_zero(cell: &str) -> bool {
+    let digits = cell.strip_prefix(['-', '+']).unwrap_or(cell);
+    let integer_part =
+        digits.split(|char: char| !char.is_ascii_digit()).next().unwrap_or_default();
+    integer_part.len() > 1 && integer_part.starts_with('0')
+}
+
+pub(crate) fn process<P>(runtime: &Runtime, pipe: &P) -> Result<P::Summary, Error>
+where
+    P: LinePipe,
+{
+    process_from(runtime, pipe.location(), pipe.new_summary())
+}
+
+pub(crate) fn process_from<S>(runtime: &Runtime, location: &DataLocation, summary: S)
+    -> Result<S, Error>
+where
+    S: Summary,
+{
+    process_lines_from(runtime, location, summary, None)
+}
+
+pub(crate) fn process_head_from<S>(runtime: &Runtime, location: &DataLocation, summary: S,
+                                   n_lines: usize) -> Result<S, Error>
+where
+    S: Summary,
+{
+    process_lines_from(runtime, location, summary, Some(n_lines))
+}
+
+fn process_lines_from<S>(runtime: &Runtime, location: &DataLocation, summary: S,
+                         max_lines: Option<usize>) -> Result<S, Error>
+where
+    S: Summary,
+{
+    match location.format {
+        DataFormat::JsonLines => process_lines(runtime, &location.location, summary, max_lines),
+        DataFormat::Tsv => {
+            let summary = TsvSummary { header: None, inner: summary };
+            let max_lines = max_lines.map(|max_lines| max_lines + 1);
+            Ok(process_lines(runtime, &location.location, summary, max_lines)?.inner)
+        }
+    }
+}
+
+fn process_lines<S>(runtime: &Runtime, location: &Location, summary: S,
+                    max_lines: Option<usize>) -> Result<S, Error>
+where
+    S: Summary,
+{
+    let mut summary = summary;
+    let mut remaining = max_lines;
+    match location {
+        Location::S3(s3uri) => {
+            for (index, s3uri) in s3::list_objects(runtime, s3uri)?.into_iter().enumerate() {
+                if remaining == Some(0) {
+                    break;
+                }
+                summary = summary.start_object(index + 1);
+                let (next_summary, count) = s3::process_from(runtime, &s3uri, summary, remaining)?;
+                summary = next_summary;
+                remaining = remaining.map(|remaining| remaining - count);
+            }
+        }
+        Location::Local(path) => {
+            for (index, file) in list_files(path)?.into_iter().enumerate() {
+                summary = summary.start_object(index + 1);
+                let reader = BufReader::new(io::open_file(&file)?);
+                for line in reader.lines() {
+                    if remaining == Some(0) {
+                        break;
+                    }
+                    summary = summary.next(line?)?.summary;
+                    remaining = remaining.map(|remaining| remaining - 1);
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+fn list_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if path.is_dir() {
+        let mut files: Vec<PathBuf> = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.is_file() {
+                files.push(file);
+            }
+        }
+        files.sort();
+        Ok(files)
+    } else {
+        Ok(vec![path.to_path_buf()])
+    }
+}
+
+pub(crate) fn size(runtime: &Runtime, location: &DataLocation) -> Result<u64, Error> {
+    match &location.location {
+        Location::S3(s3uri) => {
+            let mut size: u64 = 0;
+            for s3uri in s3::list_objects(runtime, s3uri)? {
+                size += s3::object_size(runtime, &s3uri)?;
+            }
+            Ok(size)
+        }
+        Location::Local(path) => {
+            let mut size: u64 = 0;
+            for file in list_files(path)? {
+                size += std::fs::metadata(&file)?.len();
+            }
+            Ok(size)
+        }
+    }
+}
+
+pub(crate) fn object_infos(runtime: &Runtime, location: &DataLocation)
+    -> Result<Vec<ObjectInfo>, Error> {
+    match &location.location {
+        Location::S3(s3uri) => {
+            s3::list_objects(runtime, s3uri)?.iter()
+                .map(|s3uri| s3::object_info(runtime, s3uri)).collect()
+        }
+        Location::Local(path) => {
+            list_files(path)?.iter().map(|file| {
+                let metadata = std::fs::metadata(file).map_err(|error| {
+                    Error::wrap(format!("Could not access {}", file.to_string_lossy()), error)
+                })?;
+                Ok(ObjectInfo {
+                    name: file.to_string_lossy().to_string(),
+                    size: metadata.len(),
+                    e_tag: None,
+                    version: None,
+                    last_modified: metadata.modified().ok().map(|time| {
+                        aws_sdk_s3::primitives::DateTime::from(time).to_string()
+                    }),
+                })
+            }).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Collector {
+        lines: Vec<String>,
+    }
+
+    impl Summary for Collector {
+        fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+            let mut lines = self.lines;
+            lines.push(line);
+            Ok(NextSummary { summary: Collector { lines } })
+        }
+    }
+
+    #[test]
+    fn tsv_header_is_read_again_for_each_object() {
+        let mut summary = TsvSummary { header: None, inner: Collector { lines: Vec::new() } };
+        for (index, object) in [["#a\tb", "1\tx"], ["a\tb", "2\ty"]].iter().enumerate() {
+            summary = summary.start_object(index + 1);
+            for line in object {
+                summary = summary.next(line.to_string()).unwrap().summary;
+            }
+        }
+        assert_eq!(summary.inner.lines, vec![r#"{"a":1,"b":"x"}"#, r#"{"a":2,"b":"y"}"#]);
+    }
+
+    #[test]
+    fn tsv_cells_keep_integers_and_identifiers() {
+        assert_eq!(tsv_cell_value("42"), Value::from(42));
+        assert_eq!(tsv_cell_value("-7"), Value::from(-7));
+        assert_eq!(tsv_cell_value("+5"), Value::from(5));
+        assert_eq!(tsv_cell_value("9007199254740993"), Value::from(9007199254740993i64));
+        assert_eq!(tsv_cell_value("0.25"), Value::from(0.25));
+        assert_eq!(tsv_cell_value("1e-5"), Value::from(1e-5));
+        for token in ["007", "-007", "00.5", "nan", "inf", "-Infinity", "ENSG0001"] {
+            assert_eq!(tsv_cell_value(token), Value::String(token.to_string()), "{}", token);
+        }
+        assert_eq!(tsv_cell_value("NA"), Value::Null);
+    }
+}
@@ -15,11 +15,19 @@ mod distill;
 mod vocabs;
 mod mapper;
 mod io;
+mod tabular;
+mod expr;
+mod filter;
+mod aggregate;
+mod join;
+mod location;
+mod verify;
 
 fn main() -> Result<(), Error> {
     let runtime = runtime::Runtime::new()?;
-    let command = cli::get_command_cli()?;
-    execute::execute(&runtime, &command)
+    let registry = data::Registry::load()?;
+    let command = cli::get_command_cli(&registry)?;
+    execute::execute(&runtime, &registry, &command)
 }
 
 
@@ -7,11 +7,13 @@ use penyu::model::iri::Iri;
 use penyu::model::node::{Entity, Node};
 use penyu::model::triple::Triple;
 use penyu::read::xml;
-use penyu::vocabs::rdfs;
+use penyu::vocabs::{efo, obo, rdfs};
 use std::collections::BTreeMap;
 use std::io::BufReader;
 use std::path::PathBuf;
 
+const CLO_NAMESPACE: &Iri = &Iri::new_str("http://purl.obolibrary.org/obo/CLO_");
+
 pub(crate) struct VocabFiles {
     lembic_dir: PathBuf,
     ontos_dir: PathBuf,
@@ -37,24 +39,29 @@ impl VocabFiles {
     }
     pub(crate) fn get_tissue_mapper(&self) -> Result<TissueMapper, Error> {
         let mut mappings: BTreeMap<String, Iri> = BTreeMap::new();
-        labels_to_iri(&mut mappings, &self.clo_file())?;
-        labels_to_iri(&mut mappings, &self.efo_file())?;
-        labels_to_iri(&mut mappings, &self.uberon_file())?;
-        Ok(TissueMapper::new(mappings))
+        let mut labels: BTreeMap<Iri, String> = BTreeMap::new();
+        labels_to_iri(&mut mappings, &mut labels, &self.clo_file(), CLO_NAMESPACE)?;
+        labels_to_iri(&mut mappings, &mut labels, &self.efo_file(), efo::NAMESPACE)?;
+        labels_to_iri(&mut mappings, &mut labels, &self.uberon_file(), obo::ns::UBERON)?;
+        Ok(TissueMapper::new(mappings, labels))
     }
 }
 
-pub(crate) fn labels_to_iri(mappings: &mut BTreeMap<String, Iri>, file: &PathBuf)
-    -> Result<(), Error> {
+/// Maps all labels in the file to their IRIs, and records the first label of each IRI in the
+/// file's own namespace, since the file may also label terms imported from other ontologies.
+pub(crate) fn labels_to_iri(mappings: &mut BTreeMap<String, Iri>,
+                            labels: &mut BTreeMap<Iri, String>, file: &PathBuf,
+                            namespace: &Iri) -> Result<(), Error> {
     let graph = xml::read(&mut BufReader::new(io::open_file(file)?))?;
     for triple in graph.triples() {
         let Triple { subject, predicate, object } = triple;
-        if &predicate == rdfs::LABEL {
-            if let Entity::Iri(iri) = subject {
-                if let Node::Literal(literal) = object {
-                    mappings.insert(literal.string, iri);
-                }
+        if &predicate == rdfs::LABEL
+            && let Entity::Iri(iri) = subject
+            && let Node::Literal(literal) = object {
+            if iri.strip_prefix(namespace).is_some() {
+                labels.entry(iri.clone()).or_insert_with(|| literal.string.clone());
             }
+            mappings.insert(literal.string, iri);
         }
     }
     Ok(())
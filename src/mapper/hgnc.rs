@@ -11,6 +11,7 @@ pub(crate) struct Mappers {
 }
 pub(crate) struct GeneMapper {
     mappings: BTreeMap<String, u32>,
+    symbols: BTreeMap<u32, String>,
 }
 
 pub(crate) struct ProteinMapper {
@@ -18,12 +19,17 @@ pub(crate) struct ProteinMapper {
 }
 
 impl GeneMapper {
-    pub(crate) fn new(mappings: BTreeMap<String, u32>) -> GeneMapper {
-        GeneMapper { mappings }
+    pub(crate) fn new(mappings: BTreeMap<String, u32>, symbols: BTreeMap<u32, String>)
+        -> GeneMapper {
+        GeneMapper { mappings, symbols }
     }
     pub(crate) fn map(&self, symbol: &str) -> Option<Iri> {
         self.mappings.get(symbol).map(|&hgnc_num| penyu::vocabs::hgnc::create_iri(hgnc_num))
     }
+    pub(crate) fn label(&self, iri: &Iri) -> Option<&str> {
+        let hgnc_num = iri.strip_prefix(penyu::vocabs::hgnc::NAMESPACE)?.parse::<u32>().ok()?;
+        self.symbols.get(&hgnc_num).map(|symbol| symbol.as_str())
+    }
 }
 
 impl ProteinMapper {
@@ -41,6 +47,7 @@ pub(crate) fn get_mappers(file: &Path) -> Result<Mappers, Error> {
     let mut aliases: BTreeMap<String, u32> = BTreeMap::new();
     let mut previous: BTreeMap<String, u32> = BTreeMap::new();
     let mut genes_to_proteins: BTreeMap<u32, String> = BTreeMap::new();
+    let mut approved_symbols: BTreeMap<u32, String> = BTreeMap::new();
     let reader = BufReader::new(io::open_file(file)?);
     let mut lines = reader.lines();
     let _ = lines.next(); // Skip header
@@ -60,6 +67,7 @@ pub(crate) fn get_mappers(file: &Path) -> Result<Mappers, Error> {
         let prev_symbols = extract_symbols(parts.nth(1));
         let extracted_uniprot_ids = extract_symbols(parts.nth(14));
         let uniprot_id = extracted_uniprot_ids.first();
+        approved_symbols.insert(hgnc_num, symbol.clone());
         symbols.insert(symbol, hgnc_num);
         for symbol in alias {
             aliases.insert(symbol, hgnc_num);
@@ -76,7 +84,7 @@ pub(crate) fn get_mappers(file: &Path) -> Result<Mappers, Error> {
     let gene_mappings = previous;
     let protein_mappings =
         crate_protein_mappings(&gene_mappings, &genes_to_proteins);
-    let gene_mapper = GeneMapper::new(gene_mappings);
+    let gene_mapper = GeneMapper::new(gene_mappings, approved_symbols);
     let protein_mapper = ProteinMapper::new(protein_mappings);
     Ok(Mappers { gene_mapper, protein_mapper })
 }
@@ -2,14 +2,20 @@ use penyu::model::iri::Iri;
 use std::collections::BTreeMap;
 
 pub(crate) struct TissueMapper {
-    mappings: BTreeMap<String, Iri>
+    mappings: BTreeMap<String, Iri>,
+    labels: BTreeMap<Iri, String>,
 }
 
 impl TissueMapper {
-    pub(crate) fn new(mappings: BTreeMap<String, Iri>) -> TissueMapper {
-        TissueMapper { mappings }
+    /// Labels are the ontology labels, each from the ontology the IRI belongs to.
+    pub(crate) fn new(mappings: BTreeMap<String, Iri>, labels: BTreeMap<Iri, String>)
+        -> TissueMapper {
+        TissueMapper { mappings, labels }
     }
     pub(crate) fn map(&self, label: &str) -> Option<&Iri> {
         self.mappings.get(label)
     }
+    pub(crate) fn label(&self, iri: &Iri) -> Option<&str> {
+        self.labels.get(iri).map(|label| label.as_str())
+    }
 }
\ No newline at end of file
@@ -23,6 +23,10 @@ impl Tracker {
         self.n_miss += 1;
         self.missing.insert(label);
     }
+    pub(crate) fn name(&self) -> &str { &self.name }
+    pub(crate) fn n_hit(&self) -> usize { self.n_hit }
+    pub(crate) fn n_miss(&self) -> usize { self.n_miss }
+    pub(crate) fn n_missing(&self) -> usize { self.missing.len() }
     pub(crate) fn any_notes(&self) -> bool {
         self.n_miss > 0 || self.n_hit > 0
     }
@@ -1,8 +1,12 @@
 use crate::error::Error;
-use crate::s3::S3Uri;
+use crate::location::DataLocation;
 
 pub(crate) trait Summary {
     fn next(self, line: String) -> Result<NextSummary<Self>, Error> where Self: Sized;
+    /// Called before the first line of each object or file, with its 1-based index.
+    fn start_object(self, _index: usize) -> Self where Self: Sized { self }
+    /// Called for lines consumed before reaching this summary, such as TSV headers.
+    fn skip_line(self) -> Self where Self: Sized { self }
 }
 
 pub(crate) struct NextSummary<S: Summary> {
@@ -11,7 +15,6 @@ pub(crate) struct NextSummary<S: Summary> {
 
 pub(crate) trait LinePipe {
     type Summary: Summary;
-    fn s3uri(&self) -> &S3Uri;
+    fn location(&self) -> &DataLocation;
     fn new_summary(&self) -> Self::Summary;
 }
-
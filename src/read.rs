@@ -1,40 +1,55 @@
 use crate::error::Error;
+use crate::filter::Filter;
 use crate::runtime::Runtime;
-use crate::s3::S3Uri;
+use crate::location;
+use crate::location::DataLocation;
 use crate::pipe::{LinePipe, NextSummary, Summary};
-use crate::s3;
 
-struct LinePrinterSummary {
-    count: usize
+struct LinePrinterSummary<'a> {
+    count: usize,
+    filter: Option<&'a Filter>,
 }
 
-impl LinePrinterSummary {
-    pub(crate) fn new() -> LinePrinterSummary { LinePrinterSummary { count: 0 } }
+impl LinePrinterSummary<'_> {
+    pub(crate) fn new(filter: Option<&Filter>) -> LinePrinterSummary<'_> {
+        LinePrinterSummary { count: 0, filter }
+    }
 }
 
-impl Summary for LinePrinterSummary {
+impl Summary for LinePrinterSummary<'_> {
     fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
-        println!("{}", line);
-        let summary = LinePrinterSummary { count: self.count + 1 };
-        Ok(NextSummary { summary })
+        let LinePrinterSummary { mut count, filter } = self;
+        let is_match = match filter {
+            Some(filter) => filter.matches_line(&line)?,
+            None => true,
+        };
+        if is_match {
+            println!("{}", line);
+            count += 1;
+        }
+        Ok(NextSummary { summary: LinePrinterSummary { count, filter } })
     }
 }
-struct LinePrinter {
-    location: S3Uri
+struct LinePrinter<'a> {
+    location: DataLocation,
+    filter: Option<&'a Filter>,
 }
 
-impl LinePrinter {
-    pub(crate) fn new(location: S3Uri) -> LinePrinter { LinePrinter { location } }
+impl LinePrinter<'_> {
+    pub(crate) fn new(location: DataLocation, filter: Option<&Filter>) -> LinePrinter<'_> {
+        LinePrinter { location, filter }
+    }
 }
-impl LinePipe for LinePrinter {
-    type Summary = LinePrinterSummary;
-    fn s3uri(&self) -> &S3Uri { &self.location }
-    fn new_summary(&self) -> Self::Summary { LinePrinterSummary::new() }
+impl<'a> LinePipe for LinePrinter<'a> {
+    type Summary = LinePrinterSummary<'a>;
+    fn location(&self) -> &DataLocation { &self.location }
+    fn new_summary(&self) -> Self::Summary { LinePrinterSummary::new(self.filter) }
 }
 
-pub(crate) fn print_lines(runtime: &Runtime, s3uri: &S3Uri) -> Result<(), Error> {
-    let pipe = LinePrinter::new(s3uri.clone());
-    s3::process(runtime, &pipe)?;
+pub(crate) fn print_lines(runtime: &Runtime, location: &DataLocation, filter: Option<&Filter>)
+    -> Result<(), Error> {
+    let pipe = LinePrinter::new(location.clone(), filter);
+    location::process(runtime, &pipe)?;
     Ok(())
 }
 
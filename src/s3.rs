@@ -1,6 +1,7 @@
 use std::fmt::Display;
 use crate::error::Error;
-use crate::pipe::{LinePipe, Summary};
+use crate::location::ObjectInfo;
+use crate::pipe::Summary;
 use crate::runtime::Runtime;
 use tokio::io::AsyncBufReadExt;
 
@@ -17,6 +18,7 @@ impl S3Uri {
     }
     pub(crate) fn bucket(&self) -> &str { &self.bucket }
     pub(crate) fn key(&self) -> &str { &self.key }
+    pub(crate) fn is_prefix(&self) -> bool { self.key.is_empty() || self.key.ends_with('/') }
 }
 impl TryFrom<&str> for S3Uri {
     type Error = Error;
@@ -43,24 +45,79 @@ impl Display for S3Uri {
     }
 }
 
-pub(crate) fn process<P>(runtime: &Runtime, pipe: &P) -> Result<P::Summary, Error>
+pub(crate) fn process_from<S>(runtime: &Runtime, s3uri: &S3Uri, summary: S,
+                              max_lines: Option<usize>) -> Result<(S, usize), Error>
 where
-    P: LinePipe,
+    S: Summary,
 {
-    let summary =
+    let summary_count =
         runtime.tokio().block_on(async {
             let response =
                 runtime.s3_client().get_object()
-                    .bucket(pipe.s3uri().bucket())
-                    .key(pipe.s3uri().key())
+                    .bucket(s3uri.bucket())
+                    .key(s3uri.key())
                     .send()
                     .await?;
             let mut lines = response.body.into_async_read().lines();
-            let mut summary = pipe.new_summary();
-            while let Some(line) = lines.next_line().await? {
-                summary = summary.next(line)?.summary;
+            let mut summary = summary;
+            let mut count: usize = 0;
+            while max_lines.is_none_or(|max_lines| count < max_lines) {
+                match lines.next_line().await? {
+                    Some(line) => summary = summary.next(line)?.summary,
+                    None => break,
+                }
+                count += 1;
             };
-            Ok::<P::Summary, Error>(summary)
+            Ok::<(S, usize), Error>((summary, count))
         })?;
-    Ok(summary)
+    Ok(summary_count)
+}
+
+pub(crate) fn object_size(runtime: &Runtime, s3uri: &S3Uri) -> Result<u64, Error> {
+    Ok(object_info(runtime, s3uri)?.size)
+}
+
+pub(crate) fn object_info(runtime: &Runtime, s3uri: &S3Uri) -> Result<ObjectInfo, Error> {
+    let response =
+        runtime.tokio().block_on(async {
+            runtime.s3_client().head_object()
+                .bucket(s3uri.bucket())
+                .key(s3uri.key())
+                .send()
+                .await
+        })?;
+    Ok(ObjectInfo {
+        name: s3uri.to_string(),
+        size: response.content_length().unwrap_or(0).max(0) as u64,
+        e_tag: response.e_tag().map(|e_tag| e_tag.to_string()),
+        version: response.version_id().map(|version| version.to_string()),
+        last_modified: response.last_modified().map(|time| time.to_string()),
+    })
+}
+
+pub(crate) fn list_objects(runtime: &Runtime, s3uri: &S3Uri) -> Result<Vec<S3Uri>, Error> {
+    if !s3uri.is_prefix() {
+        return Ok(vec![s3uri.clone()]);
+    }
+    let keys =
+        runtime.tokio().block_on(async {
+            let mut keys: Vec<String> = Vec::new();
+            let mut pages =
+                runtime.s3_client().list_objects_v2()
+                    .bucket(s3uri.bucket())
+                    .prefix(s3uri.key())
+                    .into_paginator()
+                    .send();
+            while let Some(page) = pages.next().await {
+                for object in page?.contents() {
+                    if let Some(key) = object.key().filter(|key| !key.ends_with('/')) {
+                        keys.push(key.to_string());
+                    }
+                }
+            }
+            Ok::<Vec<String>, Error>(keys)
+        })?;
+    let mut keys = keys;
+    keys.sort();
+    Ok(keys.into_iter().map(|key| S3Uri::new(s3uri.bucket().to_string(), key)).collect())
 }
@@ -0,0 +1,306 @@
+use crate::error::Error;
+use crate::expr::{FieldPath, Parser, Token, ValueExpr};
+use crate::filter::Filter;
+use crate::io;
+use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+mod formats {
+    pub(crate) const TSV: &str = "tsv";
+    pub(crate) const CSV: &str = "csv";
+    pub(crate) const JSONL: &str = "jsonl";
+    pub(crate) const MARKDOWN: &str = "markdown";
+    pub(crate) const ALL: [&str; 4] = [TSV, CSV, JSONL, MARKDOWN];
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum TabularFormat {
+    Tsv,
+    Csv,
+    Jsonl,
+    Markdown,
+}
+
+pub(crate) struct TabularOptions {
+    pub(crate) columns: Vec<String>,
+    pub(crate) format: TabularFormat,
+    pub(crate) output: Option<PathBuf>,
+    pub(crate) filter: Option<Filter>,
+}
+
+impl TabularOptions {
+    pub(crate) fn new() -> TabularOptions {
+        TabularOptions {
+            columns: Vec::new(),
+            format: TabularFormat::Tsv,
+            output: None,
+            filter: None,
+        }
+    }
+}
+
+impl Display for TabularFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TabularFormat::Tsv => write!(f, "{}", formats::TSV),
+            TabularFormat::Csv => write!(f, "{}", formats::CSV),
+            TabularFormat::Jsonl => write!(f, "{}", formats::JSONL),
+            TabularFormat::Markdown => write!(f, "{}", formats::MARKDOWN),
+        }
+    }
+}
+
+impl TryFrom<&str> for TabularFormat {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        match string {
+            formats::TSV => Ok(TabularFormat::Tsv),
+            formats::CSV => Ok(TabularFormat::Csv),
+            formats::JSONL => Ok(TabularFormat::Jsonl),
+            formats::MARKDOWN | "md" => Ok(TabularFormat::Markdown),
+            _ => Err(Error::from(format!(
+                "Unknown format '{}'. Known formats are '{}'.", string, formats::ALL.join("', '")
+            )))
+        }
+    }
+}
+
+pub(crate) struct ColumnSpec {
+    pub(crate) name: String,
+    expr: ValueExpr,
+}
+
+impl ColumnSpec {
+    pub(crate) fn field(name: &str) -> ColumnSpec {
+        ColumnSpec { name: name.to_string(), expr: ValueExpr::Path(FieldPath::key(name)) }
+    }
+    /// A column is a field path like `p-value` or `a.b[0].c`, or an expression like
+    /// `log10(p) as logp`. Specs without whitespace, quotes or parentheses are field paths, so
+    /// arithmetic needs spaces, as in `a - b`, and field names inside expressions that are not
+    /// plain identifiers need backticks, as in `` `p-value` * 2 ``.
+    pub(crate) fn parse(string: &str) -> Result<ColumnSpec, Error> {
+        let string = string.trim();
+        let (spec, alias) = match string.rsplit_once(" as ") {
+            Some((spec, alias)) if is_plain(spec.trim()) => (spec.trim(), Some(alias)),
+            _ => (string, None),
+        };
+        if is_plain(spec) {
+            let expr = ValueExpr::Path(FieldPath::parse(spec)?);
+            let name = match alias {
+                Some(alias) => {
+                    let mut parser = Parser::new(alias)?;
+                    let name = parse_alias(&mut parser, string)?;
+                    parser.expect_end().map_err(|error| invalid_column(string, error))?;
+                    name
+                }
+                None => spec.to_string(),
+            };
+            return Ok(ColumnSpec { name, expr });
+        }
+        let mut parser = Parser::new(string).map_err(|error| invalid_column(string, error))?;
+        let expr = parser.parse_value().map_err(|error| invalid_column(string, error))?;
+        let name =
+            if parser.next_is_keyword("as") {
+                parser.pos += 1;
+                parse_alias(&mut parser, string)?
+            } else {
+                string.to_string()
+            };
+        parser.expect_end().map_err(|error| invalid_column(string, error))?;
+        Ok(ColumnSpec { name, expr })
+    }
+    pub(crate) fn evaluate(&self, map: &Map<String, Value>) -> Value {
+        self.expr.evaluate(map).into_owned()
+    }
+}
+
+fn is_plain(spec: &str) -> bool {
+    !spec.is_empty()
+        && !spec.contains(|char: char| char.is_whitespace() || "()\"'`,".contains(char))
+}
+
+fn parse_alias(parser: &mut Parser, string: &str) -> Result<String, Error> {
+    match parser.next() {
+        Some(Token::Ident(name) | Token::Field(name) | Token::Str(name)) => Ok(name),
+        _ => Err(Error::from(format!(
+            "Invalid column '{}': expected a name after 'as'.", string
+        ))),
+    }
+}
+
+fn invalid_column(string: &str, error: Error) -> Error {
+    Error::wrap(format!(
+        "Invalid column '{}'. Field names that are not plain identifiers need backticks in \
+        expressions, like `p-value`", string
+    ), error)
+}
+
+pub(crate) struct TableWriter {
+    format: TabularFormat,
+    columns: Vec<String>,
+    writer: Box<dyn Write>,
+}
+
+impl TableWriter {
+    pub(crate) fn new(format: TabularFormat, columns: Vec<String>, output: Option<&Path>)
+        -> Result<TableWriter, Error> {
+        let writer: Box<dyn Write> = match output {
+            Some(path) => Box::new(BufWriter::new(io::create_file(path)?)),
+            None => Box::new(BufWriter::new(std::io::stdout())),
+        };
+        let mut table_writer = TableWriter { format, columns, writer };
+        table_writer.write_header()?;
+        Ok(table_writer)
+    }
+    fn write_header(&mut self) -> Result<(), Error> {
+        match self.format {
+            TabularFormat::Tsv => {
+                let cells: Vec<String> =
+                    self.columns.iter().map(|column| escape_tsv(column)).collect();
+                writeln!(self.writer, "{}", cells.join("\t"))?;
+            }
+            TabularFormat::Csv => {
+                let cells: Vec<String> =
+                    self.columns.iter().map(|column| escape_csv(column)).collect();
+                write!(self.writer, "{}\r\n", cells.join(","))?;
+            }
+            TabularFormat::Jsonl => {}
+            TabularFormat::Markdown => {
+                let cells: Vec<String> =
+                    self.columns.iter().map(|column| escape_markdown(column)).collect();
+                writeln!(self.writer, "| {} |", cells.join(" | "))?;
+                let rulers = vec!["---"; self.columns.len()];
+                writeln!(self.writer, "| {} |", rulers.join(" | "))?;
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn write_row(&mut self, row: &[Value]) -> Result<(), Error> {
+        match self.format {
+            TabularFormat::Tsv => {
+                let cells: Vec<String> =
+                    row.iter().map(|value| escape_tsv(&cell_string(value))).collect();
+                writeln!(self.writer, "{}", cells.join("\t"))?;
+            }
+            TabularFormat::Csv => {
+                let cells: Vec<String> =
+                    row.iter().map(|value| escape_csv(&cell_string(value))).collect();
+                write!(self.writer, "{}\r\n", cells.join(","))?;
+            }
+            TabularFormat::Jsonl => {
+                let mut map = Map::new();
+                for (column, value) in self.columns.iter().zip(row.iter()) {
+                    map.insert(column.clone(), value.clone());
+                }
+                writeln!(self.writer, "{}", Value::Object(map))?;
+            }
+            TabularFormat::Markdown => {
+                let cells: Vec<String> =
+                    row.iter().map(|value| escape_markdown(&cell_string(value))).collect();
+                writeln!(self.writer, "| {} |", cells.join(" | "))?;
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn finish(mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn cell_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        _ => value.to_string(),
+    }
+}
+
+pub(crate) fn escape_tsv(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for char in string.chars() {
+        match char {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+fn escape_csv(string: &str) -> String {
+    if string.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", string.replace('"', "\"\""))
+    } else {
+        string.to_string()
+    }
+}
+
+fn escape_markdown(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('|', "\\|").replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn evaluate(spec: &str, value: Value) -> (String, Value) {
+        let column = ColumnSpec::parse(spec).unwrap();
+        let value = column.evaluate(value.as_object().unwrap());
+        (column.name, value)
+    }
+
+    #[test]
+    fn plain_specs_are_field_paths() {
+        let record = json!({ "p-value": 0.01, "1kg_af": 0.2, "a": { "b": [{ "c": 3 }] } });
+        assert_eq!(evaluate("p-value", record.clone()), ("p-value".to_string(), json!(0.01)));
+        assert_eq!(evaluate("1kg_af", record.clone()), ("1kg_af".to_string(), json!(0.2)));
+        assert_eq!(evaluate("a.b[0].c", record.clone()), ("a.b[0].c".to_string(), json!(3)));
+        assert_eq!(evaluate("p-value as p", record), ("p".to_string(), json!(0.01)));
+    }
+
+    #[test]
+    fn other_specs_are_expressions() {
+        let record = json!({ "p-value": 0.01, "a": 5, "b": 2 });
+        assert_eq!(evaluate("a - b", record.clone()), ("a - b".to_string(), json!(3.0)));
+        assert_eq!(evaluate("`p-value` * 100 as percent", record.clone()),
+                   ("percent".to_string(), json!(1.0)));
+        assert_eq!(evaluate("a - b as \"a minus b\"", record),
+                   ("a minus b".to_string(), json!(3.0)));
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_newlines_and_backslashes() {
+        assert_eq!(escape_tsv("a\tb\nc\r\\d \"e\""), "a\\tb\\nc\\r\\\\d \"e\"");
+    }
+
+    #[test]
+    fn csv_quotes_cells_with_commas_quotes_and_newlines() {
+        assert_eq!(escape_csv("plain"), "plain");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv("a\nb"), "\"a\nb\"");
+        assert_eq!(escape_csv("a\tb"), "a\tb");
+    }
+
+    #[test]
+    fn markdown_escapes_pipes_and_breaks_lines() {
+        assert_eq!(escape_markdown("a|b"), "a\\|b");
+        assert_eq!(escape_markdown("a\r\nb\nc"), "a<br>b<br>c");
+        assert_eq!(escape_markdown("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn cells_show_null_as_empty_and_strings_unquoted() {
+        assert_eq!(cell_string(&Value::Null), "");
+        assert_eq!(cell_string(&json!("a")), "a");
+        assert_eq!(cell_string(&json!(1.5)), "1.5");
+    }
+}
@@ -0,0 +1,114 @@
+use crate::data::{Registry, SourceDef};
+use crate::distill::distiller::Distiller;
+use crate::error::Error;
+use crate::location;
+use crate::pipe::{NextSummary, Summary};
+use crate::runtime::Runtime;
+use std::collections::BTreeMap;
+
+const DEFAULT_N_LINES: usize = 100;
+const NOT_AVAILABLE: &str = "n/a";
+
+pub(crate) struct VerifyOptions {
+    pub(crate) n_lines: usize,
+    pub(crate) release: Option<String>,
+}
+
+pub(crate) struct LineCheck {
+    n_lines: usize,
+    n_ok: usize,
+    problems: BTreeMap<String, usize>,
+}
+
+pub(crate) struct LineCheckSummary<'a, D: Distiller> {
+    distiller: &'a D,
+    pub(crate) check: LineCheck,
+}
+
+impl VerifyOptions {
+    pub(crate) fn new() -> VerifyOptions {
+        VerifyOptions { n_lines: DEFAULT_N_LINES, release: None }
+    }
+}
+
+impl<D: Distiller> LineCheckSummary<'_, D> {
+    pub(crate) fn new(distiller: &D) -> LineCheckSummary<'_, D> {
+        let check = LineCheck { n_lines: 0, n_ok: 0, problems: BTreeMap::new() };
+        LineCheckSummary { distiller, check }
+    }
+}
+
+impl<D: Distiller> Summary for LineCheckSummary<'_, D> {
+    fn next(self, line: String) -> Result<NextSummary<Self>, Error> {
+        let LineCheckSummary { distiller, mut check } = self;
+        check.n_lines += 1;
+        match distiller.new_summary().next(line) {
+            Ok(_) => check.n_ok += 1,
+            Err(error) => *check.problems.entry(error.to_string()).or_insert(0) += 1,
+        }
+        Ok(NextSummary { summary: LineCheckSummary { distiller, check } })
+    }
+}
+
+pub(crate) fn verify_sources(runtime: &Runtime, registry: &Registry, options: &VerifyOptions)
+                             -> Result<(), Error> {
+    let mut n_failed: usize = 0;
+    for source in registry.sources() {
+        println!("{}: {} [{}]", source.name, source.location, source.distiller.name());
+        let problems = verify_source(runtime, source, options);
+        if problems.is_empty() {
+            println!("  OK");
+        } else {
+            for problem in &problems {
+                println!("  Problem: {}", problem);
+            }
+            n_failed += 1;
+        }
+    }
+    let n_sources = registry.sources().len();
+    if n_failed > 0 {
+        Err(Error::from(format!("{} of {} sources failed verification.", n_failed, n_sources)))
+    } else {
+        println!("All {} sources verified.", n_sources);
+        Ok(())
+    }
+}
+
+fn verify_source(runtime: &Runtime, source: &SourceDef, options: &VerifyOptions)
+                 -> Vec<String> {
+    let source = match source.resolve_release(options.release.as_deref()) {
+        Ok(source) => source,
+        Err(error) => return vec![error.to_string()],
+    };
+    let mut problems: Vec<String> = Vec::new();
+    match location::object_infos(runtime, &source.location) {
+        Ok(infos) if infos.is_empty() => {
+            problems.push(format!("No objects found at {}", source.location));
+            return problems;
+        }
+        Ok(infos) => {
+            for info in infos {
+                println!("  {}: {} bytes, ETag {}, last modified {}", info.name, info.size,
+                         info.e_tag.as_deref().unwrap_or(NOT_AVAILABLE),
+                         info.last_modified.as_deref().unwrap_or(NOT_AVAILABLE));
+            }
+        }
+        Err(error) => {
+            problems.push(format!("Cannot access {}: {}", source.location, error));
+            return problems;
+        }
+    }
+    match source.distiller.check_lines(runtime, &source.location, options.n_lines) {
+        Ok(check) => {
+            println!("  Parsed {} lines, {} without problems", check.n_lines, check.n_ok);
+            if check.n_lines == 0 {
+                problems.push("No lines to parse".to_string());
+            }
+            for (problem, count) in check.problems {
+                problems.push(format!("{} ({} of {} lines)", problem, count, check.n_lines));
+            }
+        }
+        Err(error) => problems.push(format!("Cannot read {}: {}", source.location, error)),
+    }
+    problems
+}
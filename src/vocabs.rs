@@ -1,14 +1,22 @@
 use penyu::model::iri::Iri;
 use penyu::syntax;
+use std::cmp::Ordering;
 
 pub(crate) const NAMESPACE: &Iri = &Iri::new_str("https://kp4cd.org/entities/");
 
 pub mod prefixes {
+    pub const KP4CD: &str = "kp4cd";
     pub const TISSUE: &str = "kp4cd-tissue";
     pub const GENE: &str = "kp4cd-gene";
     pub const DISEASE: &str = "kp4cd-disease";
     pub const VARIANT: &str = "kp4cd-variant";
     pub const PROTEIN: &str = "kp4cd-protein";
+    pub const SOURCE: &str = "kp4cd-source";
+    pub const EDGE: &str = "kp4cd-edge";
+    pub const EVIDENCE: &str = "kp4cd-evidence";
+    pub const PROV: &str = "prov";
+    pub const BIOLINK: &str = "biolink";
+    pub const SKOS: &str = "skos";
 }
 
 pub mod ns {
@@ -20,6 +28,57 @@ pub mod ns {
     pub const DISEASE: &Iri = &NAMESPACE.join_str("disease/");
     pub const VARIANT: &Iri = &NAMESPACE.join_str("variant/");
     pub const PROTEIN: &Iri = &NAMESPACE.join_str("protein/");
+    pub const SOURCE: &Iri = &NAMESPACE.join_str("source/");
+    pub const EDGE: &Iri = &NAMESPACE.join_str("edge/");
+    pub const EVIDENCE: &Iri = &NAMESPACE.join_str("evidence/");
+    pub const BIOLINK: &Iri = &Iri::new_str("https://w3id.org/biolink/vocab/");
+}
+
+pub mod props {
+    use crate::vocabs::NAMESPACE;
+    use penyu::model::iri::Iri;
+
+    pub const LOCATION: &Iri = &NAMESPACE.join_str("location");
+    pub const RELEASE: &Iri = &NAMESPACE.join_str("release");
+    pub const SOURCE: &Iri = &NAMESPACE.join_str("source");
+    pub const SCORE: &Iri = &NAMESPACE.join_str("score");
+    pub const SCORE_DIRECTION: &Iri = &NAMESPACE.join_str("score_direction");
+    pub const RECORD: &Iri = &NAMESPACE.join_str("record");
+    pub const E_TAG: &Iri = &NAMESPACE.join_str("e_tag");
+    pub const VERSION: &Iri = &NAMESPACE.join_str("version");
+    pub const SIZE: &Iri = &NAMESPACE.join_str("size");
+    pub const SUPPORTED_BY: &Iri = &NAMESPACE.join_str("supported_by");
+    pub const CONFIDENCE: &Iri = &NAMESPACE.join_str("confidence");
+    pub const BIOLINK_PREDICATE: &Iri = &NAMESPACE.join_str("biolink_predicate");
+    pub const BIOLINK_INVERSE: &Iri = &NAMESPACE.join_str("biolink_inverse");
+}
+
+pub mod reification {
+    use penyu::model::iri::Iri;
+    use penyu::vocabs::rdf::NAMESPACE;
+
+    pub const STATEMENT: &Iri = &NAMESPACE.join_str("Statement");
+    pub const SUBJECT: &Iri = &NAMESPACE.join_str("subject");
+    pub const PREDICATE: &Iri = &NAMESPACE.join_str("predicate");
+    pub const OBJECT: &Iri = &NAMESPACE.join_str("object");
+}
+
+pub mod prov {
+    use penyu::model::iri::Iri;
+
+    pub const NAMESPACE: &Iri = &Iri::new_str("http://www.w3.org/ns/prov#");
+    pub const ENTITY: &Iri = &NAMESPACE.join_str("Entity");
+    pub const COLLECTION: &Iri = &NAMESPACE.join_str("Collection");
+    pub const HAD_MEMBER: &Iri = &NAMESPACE.join_str("hadMember");
+    pub const WAS_DERIVED_FROM: &Iri = &NAMESPACE.join_str("wasDerivedFrom");
+    pub const GENERATED_AT_TIME: &Iri = &NAMESPACE.join_str("generatedAtTime");
+}
+
+pub mod skos {
+    use penyu::model::iri::Iri;
+
+    pub const NAMESPACE: &Iri = &Iri::new_str("http://www.w3.org/2004/02/skos/core#");
+    pub const ALT_LABEL: &Iri = &NAMESPACE.join_str("altLabel");
 }
 
 pub mod concepts {
@@ -33,6 +92,190 @@ pub mod concepts {
     pub const PROTEIN: &Iri = &ns::CHEBI.join_str("36080");
 }
 
+pub fn create_source_iri(name: &str) -> Iri {
+    ns::SOURCE.join(syntax::encode_local_name(&mut name.to_string().chars()))
+}
+
+pub fn create_object_iri(source: &str, index: usize) -> Iri {
+    create_source_iri(source).join(format!("/{}", index))
+}
+
+pub fn create_edge_iri(index: usize) -> Iri {
+    ns::EDGE.join(index.to_string())
+}
+
+pub fn create_evidence_iri(name: &str) -> Iri {
+    ns::EVIDENCE.join(syntax::encode_local_name(&mut name.to_string().chars()))
+}
+
+pub mod predicates {
+    use penyu::model::iri::Iri;
+    use penyu::vocabs::obo::ns;
+
+    pub const OVER_EXPRESSED_IN: &Iri = &ns::RO.join_str("0002245");
+    pub const UNDER_EXPRESSED_IN: &Iri = &ns::RO.join_str("0002246");
+    pub const DISEASE_HAS_LOCATION: &Iri = &ns::RO.join_str("0004026");
+    pub const MOLECULARLY_INTERACTS_WITH: &Iri = &ns::RO.join_str("0002436");
+    pub const INDIRECTLY_POSITIVELY_REGULATES_ACTIVITY_OF: &Iri = &ns::RO.join_str("0011013");
+    pub const CONTRIBUTES_TO_FREQUENCY_OF_CONDITION: &Iri = &ns::RO.join_str("0003306");
+    pub const ASSOCIATED_WITH: &Iri = &super::ns::BIOLINK.join_str("associated_with");
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Predicates {
+    OverExpressedIn,
+    UnderExpressedIn,
+    DiseaseHasLocation,
+    MolecularlyInteractsWith,
+    IndirectlyPositivelyRegulatesActivityOf,
+    ContributesToFrequencyOfCondition,
+    AssociatedWith,
+    Custom(&'static CustomPredicate),
+}
+
+pub struct PredicateDef {
+    pub name: &'static str,
+    pub curie: &'static str,
+    pub label: &'static str,
+    pub biolink: &'static str,
+    pub inverse: &'static str,
+    pub domain: &'static [Concepts],
+    pub range: &'static [Concepts],
+}
+
+/// A predicate defined in a source's configuration rather than in this registry.
+pub struct CustomPredicate {
+    pub iri: Iri,
+    pub def: PredicateDef,
+}
+
+impl Predicates {
+    pub const ALL: [Predicates; 7] = [
+        Predicates::OverExpressedIn,
+        Predicates::UnderExpressedIn,
+        Predicates::DiseaseHasLocation,
+        Predicates::MolecularlyInteractsWith,
+        Predicates::IndirectlyPositivelyRegulatesActivityOf,
+        Predicates::ContributesToFrequencyOfCondition,
+        Predicates::AssociatedWith,
+    ];
+    pub fn iri(&self) -> &'static Iri {
+        match self {
+            Predicates::OverExpressedIn => predicates::OVER_EXPRESSED_IN,
+            Predicates::UnderExpressedIn => predicates::UNDER_EXPRESSED_IN,
+            Predicates::DiseaseHasLocation => predicates::DISEASE_HAS_LOCATION,
+            Predicates::MolecularlyInteractsWith => predicates::MOLECULARLY_INTERACTS_WITH,
+            Predicates::IndirectlyPositivelyRegulatesActivityOf => {
+                predicates::INDIRECTLY_POSITIVELY_REGULATES_ACTIVITY_OF
+            }
+            Predicates::ContributesToFrequencyOfCondition => {
+                predicates::CONTRIBUTES_TO_FREQUENCY_OF_CONDITION
+            }
+            Predicates::AssociatedWith => predicates::ASSOCIATED_WITH,
+            Predicates::Custom(custom) => &custom.iri,
+        }
+    }
+    pub fn def(&self) -> &'static PredicateDef {
+        match self {
+            Predicates::OverExpressedIn => &PredicateDef {
+                name: "over_expressed_in",
+                curie: "RO:0002245",
+                label: "over-expressed in",
+                biolink: "biolink:expressed_in",
+                inverse: "biolink:expresses",
+                domain: &[Concepts::Gene],
+                range: &[Concepts::Tissue],
+            },
+            Predicates::UnderExpressedIn => &PredicateDef {
+                name: "under_expressed_in",
+                curie: "RO:0002246",
+                label: "under-expressed in",
+                biolink: "biolink:expressed_in",
+                inverse: "biolink:expresses",
+                domain: &[Concepts::Gene],
+                range: &[Concepts::Tissue],
+            },
+            Predicates::DiseaseHasLocation => &PredicateDef {
+                name: "disease_has_location",
+                curie: "RO:0004026",
+                label: "disease has location",
+                biolink: "biolink:disease_has_location",
+                inverse: "biolink:location_of_disease",
+                domain: &[Concepts::Disease],
+                range: &[Concepts::Tissue],
+            },
+            Predicates::MolecularlyInteractsWith => &PredicateDef {
+                name: "molecularly_interacts_with",
+                curie: "RO:0002436",
+                label: "molecularly interacts with",
+                biolink: "biolink:physically_interacts_with",
+                inverse: "biolink:physically_interacts_with",
+                domain: &[Concepts::Protein, Concepts::Gene],
+                range: &[Concepts::Protein, Concepts::Gene],
+            },
+            Predicates::IndirectlyPositivelyRegulatesActivityOf => &PredicateDef {
+                name: "indirectly_positively_regulates_activity_of",
+                curie: "RO:0011013",
+                label: "indirectly positively regulates activity of",
+                biolink: "biolink:regulates",
+                inverse: "biolink:regulated_by",
+                domain: &[Concepts::Variant],
+                range: &[Concepts::Gene],
+            },
+            Predicates::ContributesToFrequencyOfCondition => &PredicateDef {
+                name: "contributes_to_frequency_of_condition",
+                curie: "RO:0003306",
+                label: "contributes to frequency of condition",
+                biolink: "biolink:contributes_to",
+                inverse: "biolink:contribution_from",
+                domain: &[Concepts::Variant],
+                range: &[Concepts::Disease],
+            },
+            Predicates::AssociatedWith => &PredicateDef {
+                name: "associated_with",
+                curie: "biolink:associated_with",
+                label: "associated with",
+                biolink: "biolink:associated_with",
+                inverse: "biolink:associated_with",
+                domain: &[],
+                range: &[],
+            },
+            Predicates::Custom(custom) => &custom.def,
+        }
+    }
+    /// Adds a predicate for the rest of the run. It is leaked, so it can be used like the
+    /// built-in ones, which is fine because configuration is read once.
+    pub fn register(iri: Iri, def: PredicateDef) -> Predicates {
+        Predicates::Custom(Box::leak(Box::new(CustomPredicate { iri, def })))
+    }
+    pub fn find(string: &str) -> Option<Predicates> {
+        Predicates::ALL.into_iter().find(|predicate| {
+            let def = predicate.def();
+            def.name == string || def.curie == string || predicate.iri().to_string() == string
+        })
+    }
+}
+
+impl PartialEq for CustomPredicate {
+    fn eq(&self, other: &Self) -> bool { self.iri == other.iri }
+}
+
+impl Eq for CustomPredicate {}
+
+impl PartialOrd for CustomPredicate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for CustomPredicate {
+    fn cmp(&self, other: &Self) -> Ordering { self.iri.cmp(&other.iri) }
+}
+
+pub fn expand_biolink_curie(curie: &str) -> Iri {
+    let local_name = curie.strip_prefix("biolink:").unwrap_or(curie);
+    ns::BIOLINK.join(local_name.to_string())
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Concepts {
     Tissue,
     Gene,
@@ -42,6 +285,20 @@ pub enum Concepts {
 }
 
 impl Concepts {
+    pub const ALL: [Concepts; 5] =
+        [Concepts::Tissue, Concepts::Gene, Concepts::Disease, Concepts::Variant, Concepts::Protein];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Concepts::Tissue => "tissue",
+            Concepts::Gene => "gene",
+            Concepts::Disease => "disease",
+            Concepts::Variant => "variant",
+            Concepts::Protein => "protein",
+        }
+    }
+    pub fn from_concept_iri(iri: &Iri) -> Option<Concepts> {
+        Concepts::ALL.into_iter().find(|concept| concept.concept_iri() == iri)
+    }
     pub fn internal_namespace(&self) -> &'static Iri {
         match self {
             Concepts::Tissue => ns::TISSUE,